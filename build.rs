@@ -2,20 +2,28 @@ extern crate bindgen;
 extern crate cc;
 extern crate pkg_config;
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::env;
 use std::fs::{self, File};
 use std::io::{self, BufRead, BufReader, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 use std::str;
+use std::sync::{Arc, Mutex};
 
-use bindgen::callbacks::{IntKind, MacroParsingBehavior, ParseCallbacks};
+use bindgen::callbacks::{IntKind, ItemInfo, ItemKind, MacroParsingBehavior, ParseCallbacks};
+
+#[path = "build/components.rs"]
+mod components;
+#[path = "build/doxygen.rs"]
+mod doxygen;
 
 #[derive(Debug)]
 struct Library {
     name: &'static str,
     is_feature: bool,
+    // bindgen allowlist patterns of the functions, types and vars it declares
+    items: &'static [&'static str],
 }
 
 impl Library {
@@ -26,52 +34,185 @@ impl Library {
             None
         }
     }
+
+    fn is_enabled(&self) -> bool {
+        !self.is_feature || self.feature_name().and_then(|f| env::var(&f).ok()).is_some()
+    }
+
+    // Whether `name` matches one of the items, which are all exact names or
+    // prefixes followed by .*
+    fn declares(&self, name: &str) -> bool {
+        self.items.iter().any(|item| match item.strip_suffix(".*") {
+            Some(prefix) => name.starts_with(prefix),
+            None => name == *item,
+        })
+    }
 }
 
 static LIBRARIES: &[Library] = &[
     Library {
         name: "avcodec",
         is_feature: true,
+        items: &["avcodec_.*", "avsubtitle_.*", "LIBAVCODEC_.*", "RDFT.*", "DCT.*", "DV_PROFILE_.*", "PARSER_FLAG_.*", "SLICE_FLAG_.*", "VORBIS_FLAG_.*"],
     },
     Library {
         name: "avdevice",
         is_feature: true,
+        items: &["avdevice_.*", "LIBAVDEVICE_.*"],
     },
     Library {
         name: "avfilter",
         is_feature: true,
+        items: &["avfilter_.*", "LIBAVFILTER_.*"],
     },
     Library {
         name: "avformat",
         is_feature: true,
+        items: &["avformat_.*", "avio_.*", "LIBAVFORMAT_.*"],
     },
     Library {
         name: "avresample",
         is_feature: true,
+        items: &["avresample_.*", "LIBAVRESAMPLE_.*"],
     },
     Library {
         name: "avutil",
         is_feature: false,
+        items: &["av_.*", "avutil_.*", "AV.*", "FF.*", "LIBAVUTIL_.*", "AES_CTR_.*", "M_.*"],
     },
     Library {
         name: "postproc",
         is_feature: true,
+        items: &["pp_.*", "PP_.*", "postproc_.*", "LIBPOSTPROC_.*"],
     },
     Library {
         name: "swresample",
         is_feature: true,
+        items: &["swr_.*", "Swr.*", "SWR_.*", "swresample_.*", "LIBSWRESAMPLE_.*"],
     },
     Library {
         name: "swscale",
         is_feature: true,
+        items: &["sws_.*", "Sws.*", "SWS_.*", "swscale_.*", "LIBSWSCALE_.*"],
     },
 ];
 
-#[derive(Debug)]
-struct Callbacks;
+struct FlagFamily {
+    name: &'static str,
+    prefix: &'static str,
+    excluded: &'static [&'static str],
+    bits: &'static str,
+    // (struct, field, setter) holding the flags
+    fields: &'static [(&'static str, &'static str, &'static str)],
+}
+
+static FLAG_FAMILIES: &[FlagFamily] = &[
+    FlagFamily {
+        name: "AVCodecFlags",
+        prefix: "AV_CODEC_FLAG_",
+        excluded: &[],
+        bits: "::libc::c_int",
+        fields: &[("AVCodecContext", "flags", "set_flags")],
+    },
+    FlagFamily {
+        name: "AVCodecFlags2",
+        prefix: "AV_CODEC_FLAG2_",
+        excluded: &[],
+        bits: "::libc::c_int",
+        fields: &[("AVCodecContext", "flags2", "set_flags2")],
+    },
+    FlagFamily {
+        name: "AVCodecCapabilities",
+        prefix: "AV_CODEC_CAP_",
+        excluded: &[],
+        bits: "::libc::c_int",
+        fields: &[("AVCodec", "capabilities", "set_capabilities")],
+    },
+    FlagFamily {
+        name: "AVFormatContextFlags",
+        prefix: "AVFMT_FLAG_",
+        excluded: &[],
+        bits: "::libc::c_int",
+        fields: &[("AVFormatContext", "flags", "set_flags")],
+    },
+    FlagFamily {
+        name: "AVFormatFlags",
+        prefix: "AVFMT_",
+        excluded: &["AVFMT_FLAG_", "AVFMT_EVENT_FLAG_", "AVFMT_AVOID_NEG_TS_"],
+        bits: "::libc::c_int",
+        fields: &[
+            ("AVInputFormat", "flags", "set_flags"),
+            ("AVOutputFormat", "flags", "set_flags"),
+        ],
+    },
+    FlagFamily {
+        name: "AVPacketFlags",
+        prefix: "AV_PKT_FLAG_",
+        excluded: &[],
+        bits: "::libc::c_int",
+        fields: &[("AVPacket", "flags", "set_flags")],
+    },
+    FlagFamily {
+        name: "AVSeekFlags",
+        prefix: "AVSEEK_FLAG_",
+        excluded: &[],
+        bits: "::libc::c_int",
+        fields: &[],
+    },
+    FlagFamily {
+        name: "AVDictFlags",
+        prefix: "AV_DICT_",
+        excluded: &[],
+        bits: "::libc::c_int",
+        fields: &[],
+    },
+    FlagFamily {
+        name: "AVOptionFlags",
+        prefix: "AV_OPT_FLAG_",
+        excluded: &[],
+        bits: "::libc::c_int",
+        fields: &[("AVOption", "flags", "set_flags")],
+    },
+    FlagFamily {
+        name: "AVCpuFlags",
+        prefix: "AV_CPU_FLAG_",
+        excluded: &[],
+        bits: "::libc::c_int",
+        fields: &[],
+    },
+    FlagFamily {
+        name: "AVChannelMask",
+        prefix: "AV_CH_",
+        excluded: &[],
+        bits: "u64",
+        fields: &[],
+    },
+];
+
+impl FlagFamily {
+    fn contains(&self, name: &str) -> bool {
+        name.starts_with(self.prefix)
+            && !self
+                .excluded
+                .iter()
+                .any(|excluded| name.starts_with(excluded))
+    }
+}
+
+#[derive(Debug, Default)]
+struct Callbacks {
+    // every function bindgen parses, including the ones of the system headers
+    functions: Arc<Mutex<Vec<String>>>,
+    // the integer macros of the FLAG_FAMILIES
+    flags: Arc<Mutex<Vec<String>>>,
+}
 
 impl ParseCallbacks for Callbacks {
     fn int_macro(&self, name: &str, value: i64) -> Option<IntKind> {
+        if FLAG_FAMILIES.iter().any(|family| family.contains(name)) {
+            self.flags.lock().unwrap().push(name.to_string());
+        }
+
         if value >= i64::min_value() as i64
             && value <= i64::max_value() as i64
             && name.starts_with("AV_CH")
@@ -107,6 +248,23 @@ impl ParseCallbacks for Callbacks {
             _ => Default,
         }
     }
+
+    fn generated_name_override(&self, item_info: ItemInfo) -> Option<String> {
+        if let ItemKind::Function = item_info.kind {
+            if !item_info.name.starts_with('_') {
+                self.functions
+                    .lock()
+                    .unwrap()
+                    .push(item_info.name.to_string());
+            }
+        }
+
+        None
+    }
+
+    fn process_comment(&self, comment: &str) -> Option<String> {
+        Some(doxygen::to_rustdoc(comment))
+    }
 }
 
 fn version() -> String {
@@ -138,214 +296,172 @@ fn search() -> PathBuf {
     absolute
 }
 
-fn switch(configure: &mut Command, feature: &str, name: &str) {
+fn switch(feature: &str, name: &str) -> String {
     let arg = if env::var("CARGO_FEATURE_".to_string() + feature).is_ok() {
         "--enable-"
     } else {
         "--disable-"
     };
-    configure.arg(arg.to_string() + name);
+    arg.to_string() + name
+}
+
+fn ffmpeg_source_dir() -> PathBuf {
+    env::var_os("FFMPEG_SOURCE_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| env::current_dir().unwrap().join("ffmpeg"))
+}
+
+// configure's name for the target OS
+fn ffmpeg_target_os() -> String {
+    let os = env::var("CARGO_CFG_TARGET_OS").unwrap();
+    let target_env = env::var("CARGO_CFG_TARGET_ENV").unwrap_or_default();
+
+    match os.as_str() {
+        "macos" | "ios" => "darwin".to_string(),
+        "windows" if target_env == "msvc" => {
+            if env::var("CARGO_CFG_TARGET_POINTER_WIDTH").unwrap() == "64" {
+                "win64".to_string()
+            } else {
+                "win32".to_string()
+            }
+        }
+        "windows" => "mingw32".to_string(),
+        _ => os,
+    }
+}
+
+fn profiles_path() -> PathBuf {
+    Path::new(&env::var("CARGO_MANIFEST_DIR").unwrap()).join(components::PROFILES_FILE)
+}
+
+fn profiles() -> io::Result<Vec<components::Profile>> {
+    components::parse_profiles(&fs::read_to_string(profiles_path())?)
+}
+
+// Everything the source build depends on besides features, printed whether
+// or not FFmpeg gets built this time
+fn rerun_if_source_build_changed() {
+    println!("cargo:rerun-if-env-changed=FFMPEG_SOURCE_DIR");
+    println!("cargo:rerun-if-changed={}", profiles_path().display());
+    for &(var, _) in components::COMPONENT_VARS {
+        println!("cargo:rerun-if-env-changed={}", var);
+    }
+    println!("cargo:rerun-if-env-changed=FFMPEG_EXTRA_CONFIGURE_ARGS");
+}
+
+fn regenerate_scripts() -> bool {
+    println!("cargo:rerun-if-env-changed=FFMPEG_REGENERATE_SCRIPTS");
+
+    env::var("FFMPEG_REGENERATE_SCRIPTS").is_ok()
+}
+
+// Writes the ffmpeg_configure_tiny*.sh scripts from the profiles
+fn write_configure_scripts() -> io::Result<()> {
+    let profiles = profiles()?;
+    let dir = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
+
+    for &(name, extra_args) in components::SCRIPTS {
+        fs::write(dir.join(name), components::configure_script(&profiles, extra_args))?;
+    }
+
+    Ok(())
 }
 
-fn build() -> io::Result<()> {
-    let mut configure = Command::new(fs::canonicalize("./ffmpeg/configure").unwrap());
+fn configure_path() -> io::Result<PathBuf> {
+    let configure_path = ffmpeg_source_dir().join("configure");
+    fs::canonicalize(&configure_path).map_err(|e| {
+        io::Error::new(
+            e.kind(),
+            format!(
+                "FFmpeg sources not found at {} (set FFMPEG_SOURCE_DIR): {}",
+                configure_path.display(),
+                e
+            ),
+        )
+    })
+}
 
-    configure.current_dir(&source());
-    configure.arg(format!("--prefix={}", search().to_string_lossy()));
+fn configure_args(configure_path: &Path) -> io::Result<Vec<String>> {
+    let mut args = vec![format!("--prefix={}", search().to_string_lossy())];
 
     if env::var("TARGET").unwrap() != env::var("HOST").unwrap() {
-        configure.arg(format!("--cross-prefix={}-", env::var("TARGET").unwrap()));
+        args.push("--enable-cross-compile".to_string());
+        args.push(format!("--cross-prefix={}-", env::var("TARGET").unwrap()));
+        args.push(format!("--arch={}", env::var("CARGO_CFG_TARGET_ARCH").unwrap()));
+        args.push(format!("--target-os={}", ffmpeg_target_os()));
     }
 
     // control debug build
     if env::var("DEBUG").is_ok() {
-        configure.arg("--enable-debug");
-        configure.arg("--disable-stripping");
+        args.push("--enable-debug".to_string());
+        args.push("--disable-stripping".to_string());
     } else {
-        configure.arg("--disable-debug");
-        configure.arg("--enable-stripping");
+        args.push("--disable-debug".to_string());
+        args.push("--enable-stripping".to_string());
     }
 
-    // make it static
-    configure.arg("--enable-static");
-    configure.arg("--disable-shared");
+    // static or shared libraries, following the static feature
+    if env::var("CARGO_FEATURE_STATIC").is_ok() {
+        args.push("--enable-static".to_string());
+        args.push("--disable-shared".to_string());
+    } else {
+        args.push("--disable-static".to_string());
+        args.push("--enable-shared".to_string());
+
+        // found through the rpath, see link_shared
+        if env::var("CARGO_CFG_TARGET_VENDOR").unwrap() == "apple" {
+            args.push("--install-name-dir=@rpath".to_string());
+        }
+    }
 
-    configure.arg("--enable-pic");
+    args.push("--enable-pic".to_string());
 
     // do not build programs since we don't need them
-    configure.arg("--disable-programs");
+    args.push("--disable-programs".to_string());
 
     // yasm is not available on docs.rs. Build crooked version
     if env::var("CARGO_FEATURE_BUILD_DISABLE_X86ASM").is_ok() {
-        configure.arg("--disable-x86asm");
-    }
-
-    macro_rules! enable {
-        ($conf:expr, $feat:expr, $name:expr) => {
-            if env::var(concat!("CARGO_FEATURE_", $feat)).is_ok() {
-                $conf.arg(concat!("--enable-", $name));
-            } else {
-                $conf.arg(concat!("--disable-", $name));
-            }
-        };
-    }
-
-    macro_rules! enable_component {
-        ($conf:expr, $comp:expr, $name:expr) => {
-            $conf.arg(concat!("--enable-", $comp, "=", $name));
-        };
+        args.push("--disable-x86asm".to_string());
     }
 
     // the binary using ffmpeg-sys must comply with GPL
-    switch(&mut configure, "BUILD_LICENSE_GPL", "gpl");
+    args.push(switch("BUILD_LICENSE_GPL", "gpl"));
 
     // the binary using ffmpeg-sys must comply with (L)GPLv3
-    switch(&mut configure, "BUILD_LICENSE_VERSION3", "version3");
+    args.push(switch("BUILD_LICENSE_VERSION3", "version3"));
 
     // the binary using ffmpeg-sys cannot be redistributed
-    switch(&mut configure, "BUILD_LICENSE_NONFREE", "nonfree");
+    args.push(switch("BUILD_LICENSE_NONFREE", "nonfree"));
 
     // configure building libraries based on features
     for lib in LIBRARIES.iter().filter(|lib| lib.is_feature) {
-        switch(&mut configure, &lib.name.to_uppercase(), lib.name);
-    }
-
-    if env::var(concat!("CARGO_FEATURE_TINY")).is_ok() {
-        configure.arg("--disable-everything");
-        enable_component!(configure, "protocol", "file");
-        enable_component!(configure, "protocol", "pipe");
-
-        if env::var(concat!("CARGO_FEATURE_COMMON_AUDIO")).is_ok() {
-            enable_component!(configure, "demuxer", "pcm_f64be");
-            enable_component!(configure, "demuxer", "pcm_f64le");
-            enable_component!(configure, "demuxer", "pcm_f32be");
-            enable_component!(configure, "demuxer", "pcm_f32le");
-            enable_component!(configure, "demuxer", "pcm_s32be");
-            enable_component!(configure, "demuxer", "pcm_s32le");
-            enable_component!(configure, "demuxer", "pcm_s24be");
-            enable_component!(configure, "demuxer", "pcm_s24le");
-            enable_component!(configure, "demuxer", "pcm_s16be");
-            enable_component!(configure, "demuxer", "pcm_s16le");
-            enable_component!(configure, "muxer", "pcm_f64be");
-            enable_component!(configure, "muxer", "pcm_f64le");
-            enable_component!(configure, "muxer", "pcm_f32be");
-            enable_component!(configure, "muxer", "pcm_f32le");
-            enable_component!(configure, "muxer", "pcm_s32be");
-            enable_component!(configure, "muxer", "pcm_s32le");
-            enable_component!(configure, "muxer", "pcm_s24be");
-            enable_component!(configure, "muxer", "pcm_s24le");
-            enable_component!(configure, "muxer", "pcm_s16be");
-            enable_component!(configure, "muxer", "pcm_s16le");
-            enable_component!(configure, "decoder", "pcm_f32be");
-            enable_component!(configure, "decoder", "pcm_f32le");
-            enable_component!(configure, "decoder", "pcm_f64be");
-            enable_component!(configure, "decoder", "pcm_f64le");
-            enable_component!(configure, "decoder", "pcm_s16be");
-            enable_component!(configure, "decoder", "pcm_s16be_planar");
-            enable_component!(configure, "decoder", "pcm_s16le");
-            enable_component!(configure, "decoder", "pcm_s16le_planar");
-            enable_component!(configure, "decoder", "pcm_s24be");
-            enable_component!(configure, "decoder", "pcm_s24be_planar");
-            enable_component!(configure, "decoder", "pcm_s24le");
-            enable_component!(configure, "decoder", "pcm_s24le_planar");
-            enable_component!(configure, "decoder", "pcm_s32be");
-            enable_component!(configure, "decoder", "pcm_s32be_planar");
-            enable_component!(configure, "decoder", "pcm_s32le");
-            enable_component!(configure, "decoder", "pcm_s32le_planar");
-            enable_component!(configure, "encoder", "pcm_f32be");
-            enable_component!(configure, "encoder", "pcm_f32le");
-            enable_component!(configure, "encoder", "pcm_f64be");
-            enable_component!(configure, "encoder", "pcm_f64le");
-            enable_component!(configure, "encoder", "pcm_s16be");
-            enable_component!(configure, "encoder", "pcm_s16be_planar");
-            enable_component!(configure, "encoder", "pcm_s16le");
-            enable_component!(configure, "encoder", "pcm_s16le_planar");
-            enable_component!(configure, "encoder", "pcm_s24be");
-            enable_component!(configure, "encoder", "pcm_s24be_planar");
-            enable_component!(configure, "encoder", "pcm_s24le");
-            enable_component!(configure, "encoder", "pcm_s24le_planar");
-            enable_component!(configure, "encoder", "pcm_s32be");
-            enable_component!(configure, "encoder", "pcm_s32be_planar");
-            enable_component!(configure, "encoder", "pcm_s32le");
-            enable_component!(configure, "encoder", "pcm_s32le_planar");
-
-            enable_component!(configure, "demuxer", "aac");
-            enable_component!(configure, "demuxer", "aiff");
-            enable_component!(configure, "demuxer", "flac");
-            enable_component!(configure, "demuxer", "mov"); // There is no mp4 demuxer
-            enable_component!(configure, "demuxer", "mp3");
-            enable_component!(configure, "demuxer", "wav");
-            enable_component!(configure, "muxer", "aiff");
-            enable_component!(configure, "muxer", "flac");
-            enable_component!(configure, "muxer", "mp3");
-            enable_component!(configure, "muxer", "mov");
-            enable_component!(configure, "muxer", "mp4");
-            enable_component!(configure, "muxer", "wav");
-
-            enable_component!(configure, "decoder", "aac");
-            enable_component!(configure, "decoder", "aac_fixed");
-            enable_component!(configure, "decoder", "aac_latm");
-            enable_component!(configure, "decoder", "alac");
-            enable_component!(configure, "decoder", "flac");
-            enable_component!(configure, "decoder", "mp3");
-            enable_component!(configure, "encoder", "aac");
-            enable_component!(configure, "encoder", "alac");
-            enable_component!(configure, "encoder", "flac");
-
-            enable_component!(configure, "parser", "aac");
-            enable_component!(configure, "parser", "aac_latm");
-            enable_component!(configure, "parser", "flac");
-            enable_component!(configure, "parser", "mpegaudio");
-            enable_component!(configure, "parser", "vorbis");
-        }
+        args.push(switch(&lib.name.to_uppercase(), lib.name));
+    }
+
+    // component profiles, external libraries and the selected components
+    let profiles = profiles()?;
+    let selected = components::selected_components(configure_path, |var| env::var(var).ok())?;
+    args.extend(components::configure_args(
+        &profiles,
+        |feature| env::var(components::feature_var(feature)).is_ok(),
+        &selected,
+    ));
+
+    // passed on after everything else
+    if let Ok(extra) = env::var("FFMPEG_EXTRA_CONFIGURE_ARGS") {
+        args.extend(components::split_args(&extra)?);
     }
 
-    // configure external SSL libraries
-    enable!(configure, "BUILD_LIB_GNUTLS", "gnutls");
-    enable!(configure, "BUILD_LIB_OPENSSL", "openssl");
-
-    // configure external filters
-    enable!(configure, "BUILD_LIB_FONTCONFIG", "fontconfig");
-    enable!(configure, "BUILD_LIB_FREI0R", "frei0r");
-    enable!(configure, "BUILD_LIB_LADSPA", "ladspa");
-    enable!(configure, "BUILD_LIB_ASS", "libass");
-    enable!(configure, "BUILD_LIB_FREETYPE", "libfreetype");
-    enable!(configure, "BUILD_LIB_FRIBIDI", "libfribidi");
-    enable!(configure, "BUILD_LIB_OPENCV", "libopencv");
-
-    // configure external encoders/decoders
-    enable!(configure, "BUILD_LIB_CELT", "libcelt");
-    enable!(configure, "BUILD_LIB_FDK_AAC", "libfdk-aac");
-    enable!(configure, "BUILD_LIB_GSM", "libgsm");
-    enable!(configure, "BUILD_LIB_ILBC", "libilbc");
-    enable!(configure, "BUILD_LIB_KVAZAAR", "libkvazaar");
-    enable!(configure, "BUILD_LIB_MP3LAME", "libmp3lame");
-    enable!(configure, "BUILD_LIB_OPENCORE_AMRNB", "libopencore-amrnb");
-    enable!(configure, "BUILD_LIB_OPENCORE_AMRWB", "libopencore-amrwb");
-    enable!(configure, "BUILD_LIB_OPENH264", "libopenh264");
-    enable!(configure, "BUILD_LIB_OPENJPEG", "libopenjpeg");
-    enable!(configure, "BUILD_LIB_OPUS", "libopus");
-    enable!(configure, "BUILD_LIB_SHINE", "libshine");
-    enable!(configure, "BUILD_LIB_SNAPPY", "libsnappy");
-    enable!(configure, "BUILD_LIB_SPEEX", "libspeex");
-    enable!(configure, "BUILD_LIB_THEORA", "libtheora");
-    enable!(configure, "BUILD_LIB_TWOLAME", "libtwolame");
-    enable!(configure, "BUILD_LIB_VO_AMRWBENC", "libvo-amrwbenc");
-    enable!(configure, "BUILD_LIB_VORBIS", "libvorbis");
-    enable!(configure, "BUILD_LIB_VPX", "libvpx");
-    enable!(configure, "BUILD_LIB_WAVPACK", "libwavpack");
-    enable!(configure, "BUILD_LIB_WEBP", "libwebp");
-    enable!(configure, "BUILD_LIB_X264", "libx264");
-    enable!(configure, "BUILD_LIB_X265", "libx265");
-    enable!(configure, "BUILD_LIB_XVID", "libxvid");
-
-    // other external libraries
-    enable!(configure, "BUILD_NVENC", "nvenc");
-    enable!(configure, "BUILD_SDL2", "sdl2");
-
-    // configure external protocols
-    enable!(configure, "BUILD_LIB_SMBCLIENT", "libsmbclient");
-    enable!(configure, "BUILD_LIB_SSH", "libssh");
+    Ok(args)
+}
+
+fn build(configure_path: &Path, args: &[String]) -> io::Result<()> {
+    // configure runs out of tree, the sources are left untouched
+    fs::create_dir_all(source())?;
+
+    let mut configure = Command::new(configure_path);
+    configure.current_dir(source());
+    configure.args(args);
 
     // run ./configure
     let output = configure
@@ -354,47 +470,179 @@ fn build() -> io::Result<()> {
     if !output.status.success() {
         println!("configure: {}", String::from_utf8_lossy(&output.stdout));
 
-        return Err(io::Error::new(
-            io::ErrorKind::Other,
-            format!(
-                "configure failed {}",
-                String::from_utf8_lossy(&output.stderr)
-            ),
-        ));
+        return Err(io::Error::other(format!(
+            "configure failed {}",
+            String::from_utf8_lossy(&output.stderr)
+        )));
     }
 
     // run make
-    Command::new("make")
+    if !Command::new("make")
         .arg("-j")
         .arg(env::var("NUM_JOBS").unwrap_or_else(|_| "1".into()))
-        .current_dir(&source())
-        .status()
-        .map_err(|_| io::Error::new(io::ErrorKind::Other, "make failed"))?;
+        .current_dir(source())
+        .status()?
+        .success()
+    {
+        return Err(io::Error::other("make failed"));
+    }
 
     // run make install
-    Command::new("make")
-        .current_dir(&source())
+    if !Command::new("make")
+        .current_dir(source())
         .arg("install")
-        .status()
-        .map_err(|_| io::Error::new(io::ErrorKind::Other, "make install failed"))?;
+        .status()?
+        .success()
+    {
+        return Err(io::Error::other("make install failed"));
+    }
 
     Ok(())
 }
 
-fn check_features(
-    include_paths: Vec<PathBuf>,
-    infos: &Vec<(&'static str, Option<&'static str>, &'static str)>,
-) {
-    let mut includes_code = String::new();
-    let mut main_code = String::new();
+fn link_to_source_build(statik: bool) -> (Vec<PathBuf>, Vec<PathBuf>) {
+    let configure_path = configure_path().unwrap_or_else(|e| panic!("Failed to build FFmpeg: {}", e));
+    let args = configure_args(&configure_path)
+        .unwrap_or_else(|e| panic!("Failed to build FFmpeg: {}", e));
+
+    // what the libraries in dist were built with, they are rebuilt when it changes
+    let stamp = output().join("configure-args.txt");
+    let mut stamp_content = configure_path.to_string_lossy().into_owned();
+    for arg in &args {
+        stamp_content.push('\n');
+        stamp_content.push_str(arg);
+    }
 
-    for &(header, feature, var) in infos {
-        if let Some(feature) = feature {
-            if env::var(format!("CARGO_FEATURE_{}", feature.to_uppercase())).is_err() {
-                continue;
+    let lib_dir = search().join("lib");
+    let built = library_file_names("avutil", statik)
+        .iter()
+        .any(|name| lib_dir.join(name).is_file());
+    if !built || fs::read_to_string(&stamp).ok().as_ref() != Some(&stamp_content) {
+        // libraries of the previous configuration mustn't be linked
+        if search().is_dir() {
+            fs::remove_dir_all(search()).expect("Failed to remove the previous FFmpeg build");
+        }
+        build(&configure_path, &args).unwrap_or_else(|e| panic!("Failed to build FFmpeg: {}", e));
+        fs::write(&stamp, stamp_content).expect("Failed to write configure-args.txt");
+    }
+
+    println!(
+        "cargo:rustc-link-search=native={}",
+        lib_dir.to_string_lossy()
+    );
+
+    link_to_libraries(statik, &lib_dir);
+
+    (vec![search().join("include")], vec![search().join("lib")])
+}
+
+// A char array holding "[name]" followed by one digit per expression. The
+// compiler evaluates the macros, and the results are read back from the
+// object file without running anything.
+fn probe(name: &str, digits: &[String]) -> String {
+    let mut chars: Vec<String> = format!("[{}]", name)
+        .bytes()
+        .map(|byte| byte.to_string())
+        .collect();
+    chars.extend(digits.iter().map(|expr| format!("'0' + ({})", expr)));
+    chars.push("0".to_string());
+
+    format!(
+        "const char check_{}[] = {{ {} }};\n",
+        name,
+        chars.join(", ")
+    )
+}
+
+// The FF_API_* macros defined by the version headers of the enabled
+// libraries, with the header defining them.
+fn api_macros(include_paths: &Vec<PathBuf>) -> Vec<(String, String)> {
+    let mut macros: Vec<(String, String)> = Vec::new();
+
+    for library in LIBRARIES.iter().filter(|library| library.is_enabled()) {
+        for file_name in &["version.h", "version_major.h"] {
+            let header = format!("lib{}/{}", library.name, file_name);
+            let file = match File::open(search_include(include_paths, &header)) {
+                Ok(file) => file,
+                // version_major.h only exists since FFmpeg 5.1
+                Err(_) => continue,
+            };
+
+            for line in BufReader::new(file).lines() {
+                let line = line.expect("Failed to read version header");
+                let directive = match line.trim_start().strip_prefix('#') {
+                    Some(directive) => directive.trim_start(),
+                    None => continue,
+                };
+                let mut words = match directive.strip_prefix("define") {
+                    Some(rest) => rest.split_whitespace(),
+                    None => continue,
+                };
+                if let Some(name) = words.next() {
+                    if name.starts_with("FF_API_") && !macros.iter().any(|(_, var)| var == name)
+                    {
+                        macros.push((header.clone(), name.to_string()));
+                    }
+                }
+            }
+        }
+    }
+
+    macros
+}
+
+// The major and minor version ranges the version cfgs cover for each library
+static VERSION_RANGES: &[(&str, u32, u32, u32, u32)] = &[
+    ("avutil", 56, 60, 0, 80),
+    ("avcodec", 58, 61, 0, 140),
+    ("avformat", 58, 61, 0, 80),
+    ("swresample", 3, 5, 0, 12),
+    ("swscale", 5, 8, 0, 12),
+    ("avfilter", 7, 10, 0, 120),
+    ("avdevice", 58, 61, 0, 15),
+];
+
+fn check_cfg(name: &str, values: &[String]) -> String {
+    let values: Vec<String> = values.iter().map(|value| format!("{:?}", value)).collect();
+    format!(
+        "cargo::rustc-check-cfg=cfg({}, values({}))",
+        name,
+        values.join(", ")
+    )
+}
+
+// Compiles the probes and reads their values back from the objects. They are
+// only compiled, never linked or run, so this works when cross-compiling.
+fn compile_probes(path: &Path, include_paths: &[PathBuf]) -> String {
+    // With LTO the objects would hold compiler IR instead of the probe data.
+    let objects = cc::Build::new()
+        .file(path)
+        .includes(include_paths)
+        .cargo_metadata(false)
+        .warnings(false)
+        .flag_if_supported("-fno-lto")
+        .try_compile_intermediates()
+        .unwrap_or_else(|e| panic!("Compile failed: {}", e));
+
+    let mut stdout = String::new();
+    for object in objects {
+        let data = fs::read(&object).expect("Failed to read check object");
+        for chunk in data.split(|&byte| byte == 0) {
+            if let Some(start) = chunk.iter().rposition(|&byte| byte == b'[') {
+                stdout.push_str(&String::from_utf8_lossy(&chunk[start..]));
+                stdout.push('\n');
             }
         }
+    }
 
+    stdout
+}
+
+fn check_features(include_paths: Vec<PathBuf>, infos: &[(String, String)]) -> Vec<String> {
+    let mut includes_code = String::new();
+    let mut probes_code = String::new();
+
+    for (header, var) in infos {
         let include = format!("#include <{}>", header);
         if includes_code.find(&include).is_none() {
             includes_code.push_str(&include);
@@ -412,27 +660,38 @@ fn check_features(
             var = var
         ));
 
-        main_code.push_str(&format!(
-            r#"printf("[{var}]%d%d\n", {var}, {var}_is_defined);"#,
-            var = var
+        probes_code.push_str(&probe(
+            var,
+            &[format!("!!({})", var), format!("{}_is_defined", var)],
         ));
     }
 
-    let version_check_info = [("avutil", 56, 60, 0, 80)];
-    for &(lib, begin_version_major, end_version_major, begin_version_minor, end_version_minor) in
-        version_check_info.iter()
-    {
-        for version_major in begin_version_major..end_version_major {
-            for version_minor in begin_version_minor..end_version_minor {
-                main_code.push_str(&format!(
-                                r#"printf("[{lib}_version_greater_than_{version_major}_{version_minor}]%d\n", LIB{lib_uppercase}_VERSION_MAJOR > {version_major} || (LIB{lib_uppercase}_VERSION_MAJOR == {version_major} && LIB{lib_uppercase}_VERSION_MINOR > {version_minor}));"#,
-                                lib = lib,
-                                lib_uppercase = lib.to_uppercase(),
-                                version_major = version_major,
-                                version_minor = version_minor
-                        ));
-            }
+    let version_check_info: Vec<_> = VERSION_RANGES
+        .iter()
+        .cloned()
+        .filter(|&(lib, ..)| {
+            LIBRARIES
+                .iter()
+                .any(|library| library.name == lib && library.is_enabled())
+        })
+        .collect();
+
+    for &(lib, ..) in version_check_info.iter() {
+        let include = format!("#include <lib{}/version.h>", lib);
+        if includes_code.find(&include).is_none() {
+            includes_code.push_str(&include);
+            includes_code.push('\n');
+        }
+
+        // three digits for each of major, minor and micro
+        let mut digits = Vec::new();
+        for part in &["MAJOR", "MINOR", "MICRO"] {
+            let macro_name = format!("LIB{}_VERSION_{}", lib.to_uppercase(), part);
+            digits.push(format!("{} / 100 % 10", macro_name));
+            digits.push(format!("{} / 10 % 10", macro_name));
+            digits.push(format!("{} % 10", macro_name));
         }
+        probes_code.push_str(&probe(&format!("{}_version", lib), &digits));
     }
 
     let out_dir = output();
@@ -440,98 +699,131 @@ fn check_features(
     write!(
         File::create(out_dir.join("check.c")).expect("Failed to create file"),
         r#"
-            #include <stdio.h>
             {includes_code}
 
-            int main()
-            {{
-                {main_code}
-                return 0;
-            }}
+            {probes_code}
            "#,
         includes_code = includes_code,
-        main_code = main_code
+        probes_code = probes_code
     )
     .expect("Write failed");
 
-    let executable = out_dir.join(if cfg!(windows) { "check.exe" } else { "check" });
-    let mut compiler = cc::Build::new().get_compiler().to_command();
-
-    for dir in include_paths {
-        compiler.arg("-I");
-        compiler.arg(dir.to_string_lossy().into_owned());
-    }
-    if !compiler
-        .current_dir(&out_dir)
-        .arg("-o")
-        .arg(&executable)
-        .arg("check.c")
-        .status()
-        .expect("Command failed")
-        .success()
-    {
-        panic!("Compile failed");
-    }
-
-    let stdout_raw = Command::new(out_dir.join(&executable))
-        .current_dir(&out_dir)
-        .output()
-        .expect("Check failed")
-        .stdout;
-    let stdout = str::from_utf8(stdout_raw.as_slice()).unwrap();
-
+    // the pregenerated bindings come with the probes, so no C compiler is needed
+    let stdout = if pregenerated_bindings() {
+        fs::read_to_string(out_dir.join("probes.txt")).expect("Failed to read probes.txt")
+    } else {
+        let stdout = compile_probes(&out_dir.join("check.c"), &include_paths);
+        fs::write(out_dir.join("probes.txt"), &stdout).expect("Couldn't write probes.txt");
+        stdout
+    };
     println!("stdout={}", stdout);
 
-    for &(_, feature, var) in infos {
-        if let Some(feature) = feature {
-            if env::var(format!("CARGO_FEATURE_{}", feature.to_uppercase())).is_err() {
-                continue;
-            }
-        }
+    let legacy = env::var("CARGO_FEATURE_LEGACY_FEATURE_CFGS").is_ok();
+    let mut cfgs = Vec::new();
+    let mut legacy_names = Vec::new();
 
+    let api_names: Vec<String> = infos
+        .iter()
+        .map(|(_, var)| var["FF_API_".len()..].to_lowercase())
+        .collect();
+    cfgs.push(check_cfg("ffmpeg_api", &api_names));
+    cfgs.push(check_cfg("ffmpeg_api_defined", &api_names));
+
+    for ((_, var), name) in infos.iter().zip(&api_names) {
         let var_str = format!("[{var}]", var = var);
-        let pos = stdout.find(&var_str).expect("Variable not found in output") + var_str.len();
+        let pos = stdout
+            .find(&var_str)
+            .unwrap_or_else(|| panic!("Probe {} not found in the check object", var))
+            + var_str.len();
         if &stdout[pos..pos + 1] == "1" {
-            println!(r#"cargo:rustc-cfg=feature="{}""#, var.to_lowercase());
-            println!(r#"cargo:{}=true"#, var.to_lowercase());
+            cfgs.push(format!(r#"cargo:rustc-cfg=ffmpeg_api="{}""#, name));
+            cfgs.push(format!(r#"cargo:{}=true"#, var.to_lowercase()));
+            legacy_names.push(var.to_lowercase());
         }
 
         // Also find out if defined or not (useful for cases where only the definition of a macro
         // can be used as distinction)
         if &stdout[pos + 1..pos + 2] == "1" {
-            println!(
-                r#"cargo:rustc-cfg=feature="{}_is_defined""#,
-                var.to_lowercase()
-            );
-            println!(r#"cargo:{}_is_defined=true"#, var.to_lowercase());
+            cfgs.push(format!(r#"cargo:rustc-cfg=ffmpeg_api_defined="{}""#, name));
+            cfgs.push(format!(r#"cargo:{}_is_defined=true"#, var.to_lowercase()));
+            legacy_names.push(format!("{}_is_defined", var.to_lowercase()));
         }
     }
 
+    let mut versions = HashMap::new();
+    let mut versions_code = String::new();
+    for &(lib, ..) in version_check_info.iter() {
+        let search_str = format!("[{}_version]", lib);
+        let pos = stdout
+            .find(&search_str)
+            .unwrap_or_else(|| panic!("Probe {}_version not found in the check object", lib))
+            + search_str.len();
+        let version: Vec<u32> = (0..3)
+            .map(|i| {
+                stdout[pos + i * 3..pos + i * 3 + 3]
+                    .parse()
+                    .expect("Invalid version in output")
+            })
+            .collect();
+
+        cfgs.push(format!(
+            "cargo:{}_version={}.{}.{}",
+            lib, version[0], version[1], version[2]
+        ));
+        versions.insert(lib, (version[0], version[1]));
+        for (part, value) in ["MAJOR", "MINOR", "MICRO"].iter().zip(version) {
+            versions_code.push_str(&format!(
+                "pub const {}_VERSION_{}: u32 = {};\n",
+                lib.to_uppercase(),
+                part,
+                value
+            ));
+        }
+    }
+    fs::write(out_dir.join("versions.rs"), versions_code).expect("Couldn't write versions.rs");
+
     for &(lib, begin_version_major, end_version_major, begin_version_minor, end_version_minor) in
-        version_check_info.iter()
+        VERSION_RANGES
     {
+        let cfg_name = format!("ffmpeg_{}_version_at_least", lib);
+        let mut values = Vec::new();
+        for version_major in begin_version_major..end_version_major {
+            for version_minor in begin_version_minor..end_version_minor {
+                values.push(format!("{}.{}", version_major, version_minor));
+            }
+        }
+        cfgs.push(check_cfg(&cfg_name, &values));
+
+        let version = match versions.get(lib) {
+            Some(version) => *version,
+            None => continue,
+        };
         for version_major in begin_version_major..end_version_major {
             for version_minor in begin_version_minor..end_version_minor {
-                let search_str = format!(
-                    "[{lib}_version_greater_than_{version_major}_{version_minor}]",
-                    version_major = version_major,
-                    version_minor = version_minor,
-                    lib = lib
-                );
-                let pos = stdout
-                    .find(&search_str)
-                    .expect("Variable not found in output")
-                    + search_str.len();
-
-                if &stdout[pos..pos + 1] == "1" {
-                    println!(
-                        r#"cargo:rustc-cfg=feature="{}""#,
-                        &search_str[1..(search_str.len() - 1)]
-                    );
+                if version >= (version_major, version_minor) {
+                    cfgs.push(format!(
+                        r#"cargo:rustc-cfg={}="{}.{}""#,
+                        cfg_name, version_major, version_minor
+                    ));
+                }
+                if version > (version_major, version_minor) {
+                    legacy_names.push(format!(
+                        "{}_version_greater_than_{}_{}",
+                        lib, version_major, version_minor
+                    ));
                 }
             }
         }
     }
+
+    if legacy {
+        cfgs.push(check_cfg("feature", &legacy_names));
+        for name in &legacy_names {
+            cfgs.push(format!(r#"cargo:rustc-cfg=feature="{}""#, name));
+        }
+    }
+
+    cfgs
 }
 
 fn search_include(include_paths: &Vec<PathBuf>, header: &str) -> String {
@@ -544,350 +836,1175 @@ fn search_include(include_paths: &Vec<PathBuf>, header: &str) -> String {
     format!("/usr/include/{}", header)
 }
 
-fn link_to_libraries(statik: bool) {
-    let ffmpeg_ty = if statik { "static" } else { "dylib" };
+#[derive(Debug, Default)]
+struct PkgConfigFile {
+    requires: Vec<String>,
+    libs: Vec<String>,
+}
 
-    if cfg!(windows) {
-        println!("cargo:rustc-link-lib={}={}", ffmpeg_ty, "libmp3lame-static");
-        println!("cargo:rustc-link-lib={}={}", ffmpeg_ty, "libmpghip-static");
-        println!("cargo:rustc-link-lib=bcrypt");
-        println!("cargo:rustc-link-lib=User32");
-    } else {
-        println!("cargo:rustc-link-lib={}={}", ffmpeg_ty, "mp3lame");
+impl PkgConfigFile {
+    fn parse(path: &Path, statik: bool) -> io::Result<PkgConfigFile> {
+        let mut variables = HashMap::new();
+        variables.insert(
+            "pcfiledir".to_string(),
+            path.parent().unwrap().to_string_lossy().into_owned(),
+        );
+
+        let mut pc = PkgConfigFile::default();
+        for line in BufReader::new(File::open(path)?).lines() {
+            let line = line?;
+            let line = line.trim();
+            if line.starts_with('#') {
+                continue;
+            }
+
+            let pos = match line.find([':', '=']) {
+                Some(pos) => pos,
+                None => continue,
+            };
+            let key = line[..pos].trim();
+            let value = expand_pc_variables(line[pos + 1..].trim(), &variables);
+
+            if line[pos..].starts_with('=') {
+                variables.insert(key.to_string(), value);
+                continue;
+            }
+
+            match key {
+                "Requires" => pc.requires.extend(parse_pc_requires(&value)),
+                "Requires.private" if statik => pc.requires.extend(parse_pc_requires(&value)),
+                "Libs" => pc.libs.extend(split_pc_args(&value)),
+                "Libs.private" if statik => pc.libs.extend(split_pc_args(&value)),
+                _ => {}
+            }
+        }
+
+        Ok(pc)
     }
+}
 
-    for lib in LIBRARIES {
-        let feat_is_enabled = lib.feature_name().and_then(|f| env::var(&f).ok()).is_some();
-        if !lib.is_feature || feat_is_enabled {
-            println!("cargo:rustc-link-lib={}={}", ffmpeg_ty, lib.name);
+fn expand_pc_variables(value: &str, variables: &HashMap<String, String>) -> String {
+    let mut expanded = String::new();
+    let mut rest = value;
+
+    while let Some(start) = rest.find("${") {
+        let end = match rest[start..].find('}') {
+            Some(end) => start + end,
+            None => break,
+        };
+
+        expanded.push_str(&rest[..start]);
+        if let Some(var) = variables.get(&rest[start + 2..end]) {
+            expanded.push_str(var);
         }
+        rest = &rest[end + 1..];
     }
-    if env::var("CARGO_FEATURE_BUILD_ZLIB").is_ok() && cfg!(target_os = "linux") {
-        println!("cargo:rustc-link-lib=z");
+    expanded.push_str(rest);
+
+    expanded
+}
+
+// "libavcodec >= 59.37.100, libavutil >= 57.28.100" -> ["libavcodec", "libavutil"]
+fn parse_pc_requires(value: &str) -> Vec<String> {
+    let mut requires = Vec::new();
+    let mut tokens = value
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|token| !token.is_empty());
+
+    while let Some(token) = tokens.next() {
+        match token {
+            "=" | "!=" | "<" | "<=" | ">" | ">=" => {
+                tokens.next();
+            }
+            name => requires.push(name.to_string()),
+        }
     }
+
+    requires
 }
 
-fn main() {
-    let statik = env::var("CARGO_FEATURE_STATIC").is_ok();
-    let target_triple = env::var("TARGET").unwrap();
+fn split_pc_args(value: &str) -> Vec<String> {
+    value
+        .split_whitespace()
+        .map(|arg| arg.trim_matches('"').to_string())
+        .collect()
+}
 
-    let include_paths: Vec<PathBuf> = {
-        let mut ffmpeg_dir = env::current_dir().unwrap();
+// Visits `name` after everything it requires, so `order` ends up with
+// dependencies before their dependents.
+fn visit_pc_file(
+    pkgconfig_dir: &Path,
+    name: &str,
+    statik: bool,
+    visited: &mut HashSet<String>,
+    order: &mut Vec<PkgConfigFile>,
+) {
+    if !visited.insert(name.to_string()) {
+        return;
+    }
 
-        ffmpeg_dir.push("builds");
-        ffmpeg_dir.push(target_triple);
+    let path = pkgconfig_dir.join(format!("{}.pc", name));
+    let pc = if path.is_file() {
+        PkgConfigFile::parse(&path, statik)
+            .unwrap_or_else(|e| panic!("Failed to read {}: {}", path.display(), e))
+    } else {
         println!(
-            "cargo:rustc-link-search=native={}",
-            ffmpeg_dir.join("lib").to_string_lossy()
+            "cargo:warning={} not found, linking {} without its dependencies",
+            path.display(),
+            name
         );
-        link_to_libraries(statik);
-        vec![ffmpeg_dir.join("include")]
+
+        PkgConfigFile {
+            requires: Vec::new(),
+            libs: vec![format!("-l{}", name.trim_start_matches("lib"))],
+        }
     };
 
-    check_features(
-        include_paths.clone(),
-        &vec![
-            ("libavutil/avutil.h", None, "FF_API_OLD_AVOPTIONS"),
-            ("libavutil/avutil.h", None, "FF_API_PIX_FMT"),
-            ("libavutil/avutil.h", None, "FF_API_CONTEXT_SIZE"),
-            ("libavutil/avutil.h", None, "FF_API_PIX_FMT_DESC"),
-            ("libavutil/avutil.h", None, "FF_API_AV_REVERSE"),
-            ("libavutil/avutil.h", None, "FF_API_AUDIOCONVERT"),
-            ("libavutil/avutil.h", None, "FF_API_CPU_FLAG_MMX2"),
-            ("libavutil/avutil.h", None, "FF_API_LLS_PRIVATE"),
-            ("libavutil/avutil.h", None, "FF_API_AVFRAME_LAVC"),
-            ("libavutil/avutil.h", None, "FF_API_VDPAU"),
-            (
-                "libavutil/avutil.h",
-                None,
-                "FF_API_GET_CHANNEL_LAYOUT_COMPAT",
-            ),
-            ("libavutil/avutil.h", None, "FF_API_XVMC"),
-            ("libavutil/avutil.h", None, "FF_API_OPT_TYPE_METADATA"),
-            ("libavutil/avutil.h", None, "FF_API_DLOG"),
-            ("libavutil/avutil.h", None, "FF_API_HMAC"),
-            ("libavutil/avutil.h", None, "FF_API_VAAPI"),
-            ("libavutil/avutil.h", None, "FF_API_PKT_PTS"),
-            ("libavutil/avutil.h", None, "FF_API_ERROR_FRAME"),
-            ("libavutil/avutil.h", None, "FF_API_FRAME_QP"),
-            (
-                "libavcodec/avcodec.h",
-                Some("avcodec"),
-                "FF_API_VIMA_DECODER",
-            ),
-            (
-                "libavcodec/avcodec.h",
-                Some("avcodec"),
-                "FF_API_REQUEST_CHANNELS",
-            ),
-            (
-                "libavcodec/avcodec.h",
-                Some("avcodec"),
-                "FF_API_OLD_DECODE_AUDIO",
-            ),
-            (
-                "libavcodec/avcodec.h",
-                Some("avcodec"),
-                "FF_API_OLD_ENCODE_AUDIO",
-            ),
-            (
-                "libavcodec/avcodec.h",
-                Some("avcodec"),
-                "FF_API_OLD_ENCODE_VIDEO",
-            ),
-            ("libavcodec/avcodec.h", Some("avcodec"), "FF_API_CODEC_ID"),
-            (
-                "libavcodec/avcodec.h",
-                Some("avcodec"),
-                "FF_API_AUDIO_CONVERT",
-            ),
-            (
-                "libavcodec/avcodec.h",
-                Some("avcodec"),
-                "FF_API_AVCODEC_RESAMPLE",
-            ),
-            (
-                "libavcodec/avcodec.h",
-                Some("avcodec"),
-                "FF_API_DEINTERLACE",
-            ),
-            (
-                "libavcodec/avcodec.h",
-                Some("avcodec"),
-                "FF_API_DESTRUCT_PACKET",
-            ),
-            ("libavcodec/avcodec.h", Some("avcodec"), "FF_API_GET_BUFFER"),
-            (
-                "libavcodec/avcodec.h",
-                Some("avcodec"),
-                "FF_API_MISSING_SAMPLE",
-            ),
-            ("libavcodec/avcodec.h", Some("avcodec"), "FF_API_LOWRES"),
-            ("libavcodec/avcodec.h", Some("avcodec"), "FF_API_CAP_VDPAU"),
-            ("libavcodec/avcodec.h", Some("avcodec"), "FF_API_BUFS_VDPAU"),
-            ("libavcodec/avcodec.h", Some("avcodec"), "FF_API_VOXWARE"),
-            (
-                "libavcodec/avcodec.h",
-                Some("avcodec"),
-                "FF_API_SET_DIMENSIONS",
-            ),
-            ("libavcodec/avcodec.h", Some("avcodec"), "FF_API_DEBUG_MV"),
-            ("libavcodec/avcodec.h", Some("avcodec"), "FF_API_AC_VLC"),
-            (
-                "libavcodec/avcodec.h",
-                Some("avcodec"),
-                "FF_API_OLD_MSMPEG4",
-            ),
-            (
-                "libavcodec/avcodec.h",
-                Some("avcodec"),
-                "FF_API_ASPECT_EXTENDED",
-            ),
-            (
-                "libavcodec/avcodec.h",
-                Some("avcodec"),
-                "FF_API_THREAD_OPAQUE",
-            ),
-            ("libavcodec/avcodec.h", Some("avcodec"), "FF_API_CODEC_PKT"),
-            ("libavcodec/avcodec.h", Some("avcodec"), "FF_API_ARCH_ALPHA"),
-            ("libavcodec/avcodec.h", Some("avcodec"), "FF_API_ERROR_RATE"),
-            (
-                "libavcodec/avcodec.h",
-                Some("avcodec"),
-                "FF_API_QSCALE_TYPE",
-            ),
-            ("libavcodec/avcodec.h", Some("avcodec"), "FF_API_MB_TYPE"),
-            (
-                "libavcodec/avcodec.h",
-                Some("avcodec"),
-                "FF_API_MAX_BFRAMES",
-            ),
-            (
-                "libavcodec/avcodec.h",
-                Some("avcodec"),
-                "FF_API_NEG_LINESIZES",
-            ),
-            ("libavcodec/avcodec.h", Some("avcodec"), "FF_API_EMU_EDGE"),
-            ("libavcodec/avcodec.h", Some("avcodec"), "FF_API_ARCH_SH4"),
-            ("libavcodec/avcodec.h", Some("avcodec"), "FF_API_ARCH_SPARC"),
-            (
-                "libavcodec/avcodec.h",
-                Some("avcodec"),
-                "FF_API_UNUSED_MEMBERS",
-            ),
-            (
-                "libavcodec/avcodec.h",
-                Some("avcodec"),
-                "FF_API_IDCT_XVIDMMX",
-            ),
-            (
-                "libavcodec/avcodec.h",
-                Some("avcodec"),
-                "FF_API_INPUT_PRESERVED",
-            ),
-            (
-                "libavcodec/avcodec.h",
-                Some("avcodec"),
-                "FF_API_NORMALIZE_AQP",
-            ),
-            ("libavcodec/avcodec.h", Some("avcodec"), "FF_API_GMC"),
-            ("libavcodec/avcodec.h", Some("avcodec"), "FF_API_MV0"),
-            ("libavcodec/avcodec.h", Some("avcodec"), "FF_API_CODEC_NAME"),
-            ("libavcodec/avcodec.h", Some("avcodec"), "FF_API_AFD"),
-            ("libavcodec/avcodec.h", Some("avcodec"), "FF_API_VISMV"),
-            (
-                "libavcodec/avcodec.h",
-                Some("avcodec"),
-                "FF_API_DV_FRAME_PROFILE",
-            ),
-            (
-                "libavcodec/avcodec.h",
-                Some("avcodec"),
-                "FF_API_AUDIOENC_DELAY",
-            ),
-            (
-                "libavcodec/avcodec.h",
-                Some("avcodec"),
-                "FF_API_VAAPI_CONTEXT",
-            ),
-            (
-                "libavcodec/avcodec.h",
-                Some("avcodec"),
-                "FF_API_AVCTX_TIMEBASE",
-            ),
-            ("libavcodec/avcodec.h", Some("avcodec"), "FF_API_MPV_OPT"),
-            (
-                "libavcodec/avcodec.h",
-                Some("avcodec"),
-                "FF_API_STREAM_CODEC_TAG",
-            ),
-            ("libavcodec/avcodec.h", Some("avcodec"), "FF_API_QUANT_BIAS"),
-            (
-                "libavcodec/avcodec.h",
-                Some("avcodec"),
-                "FF_API_RC_STRATEGY",
-            ),
-            (
-                "libavcodec/avcodec.h",
-                Some("avcodec"),
-                "FF_API_CODED_FRAME",
-            ),
-            ("libavcodec/avcodec.h", Some("avcodec"), "FF_API_MOTION_EST"),
-            (
-                "libavcodec/avcodec.h",
-                Some("avcodec"),
-                "FF_API_WITHOUT_PREFIX",
-            ),
-            (
-                "libavcodec/avcodec.h",
-                Some("avcodec"),
-                "FF_API_CONVERGENCE_DURATION",
-            ),
-            (
-                "libavcodec/avcodec.h",
-                Some("avcodec"),
-                "FF_API_PRIVATE_OPT",
-            ),
-            ("libavcodec/avcodec.h", Some("avcodec"), "FF_API_CODER_TYPE"),
-            (
-                "libavcodec/avcodec.h",
-                Some("avcodec"),
-                "FF_API_RTP_CALLBACK",
-            ),
-            ("libavcodec/avcodec.h", Some("avcodec"), "FF_API_STAT_BITS"),
-            ("libavcodec/avcodec.h", Some("avcodec"), "FF_API_VBV_DELAY"),
-            (
-                "libavcodec/avcodec.h",
-                Some("avcodec"),
-                "FF_API_SIDEDATA_ONLY_PKT",
-            ),
-            ("libavcodec/avcodec.h", Some("avcodec"), "FF_API_AVPICTURE"),
-            (
-                "libavformat/avformat.h",
-                Some("avformat"),
-                "FF_API_LAVF_BITEXACT",
-            ),
-            (
-                "libavformat/avformat.h",
-                Some("avformat"),
-                "FF_API_LAVF_FRAC",
-            ),
-            (
-                "libavformat/avformat.h",
-                Some("avformat"),
-                "FF_API_URL_FEOF",
-            ),
-            (
-                "libavformat/avformat.h",
-                Some("avformat"),
-                "FF_API_PROBESIZE_32",
-            ),
-            (
-                "libavformat/avformat.h",
-                Some("avformat"),
-                "FF_API_LAVF_AVCTX",
-            ),
-            (
-                "libavformat/avformat.h",
-                Some("avformat"),
-                "FF_API_OLD_OPEN_CALLBACKS",
-            ),
-            (
-                "libavfilter/avfilter.h",
-                Some("avfilter"),
-                "FF_API_AVFILTERPAD_PUBLIC",
-            ),
-            (
-                "libavfilter/avfilter.h",
-                Some("avfilter"),
-                "FF_API_FOO_COUNT",
-            ),
-            (
-                "libavfilter/avfilter.h",
-                Some("avfilter"),
-                "FF_API_OLD_FILTER_OPTS",
-            ),
-            (
-                "libavfilter/avfilter.h",
-                Some("avfilter"),
-                "FF_API_OLD_FILTER_OPTS_ERROR",
-            ),
-            (
-                "libavfilter/avfilter.h",
-                Some("avfilter"),
-                "FF_API_AVFILTER_OPEN",
-            ),
-            (
-                "libavfilter/avfilter.h",
-                Some("avfilter"),
-                "FF_API_OLD_FILTER_REGISTER",
-            ),
-            (
-                "libavfilter/avfilter.h",
-                Some("avfilter"),
-                "FF_API_OLD_GRAPH_PARSE",
-            ),
-            (
-                "libavfilter/avfilter.h",
-                Some("avfilter"),
-                "FF_API_NOCONST_GET_NAME",
-            ),
-            (
-                "libavresample/avresample.h",
-                Some("avresample"),
-                "FF_API_RESAMPLE_CLOSE_OPEN",
-            ),
-            (
-                "libswscale/swscale.h",
-                Some("swscale"),
-                "FF_API_SWS_CPU_CAPS",
-            ),
-            ("libswscale/swscale.h", Some("swscale"), "FF_API_ARCH_BFIN"),
-        ],
-    );
-    // For debugging purpose only.
-    let tmp = std::env::temp_dir();
-    let mut f = File::create(tmp.join("ffmpeg4.build")).expect("Filed to create ffmpeg4.build");
+    for dep in &pc.requires {
+        visit_pc_file(pkgconfig_dir, dep, statik, visited, order);
+    }
+    order.push(pc);
+}
+
+fn link_lib_directive(name: &str, statik: bool, lib_dir: &Path) -> String {
+    // archives shipped next to ffmpeg are linked the same way ffmpeg is
+    let archives = [format!("lib{}.a", name), format!("{}.lib", name)];
+    if archives.iter().any(|archive| lib_dir.join(archive).is_file()) {
+        let kind = if statik { "static" } else { "dylib" };
+        return format!("cargo:rustc-link-lib={}={}", kind, name);
+    }
+
+    // vcpkg names its static lame build libmp3lame-static.lib
+    if lib_dir.join(format!("{}-static.lib", name)).is_file() {
+        return format!("cargo:rustc-link-lib=static={}-static", name);
+    }
+
+    format!("cargo:rustc-link-lib={}", name)
+}
+
+fn link_directives(args: &[String], statik: bool, lib_dir: &Path) -> Vec<String> {
+    let mut directives = Vec::new();
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        if arg == "-framework" {
+            if let Some(framework) = args.next() {
+                directives.push(format!("cargo:rustc-link-lib=framework={}", framework));
+            }
+        } else if arg == "-pthread" {
+            directives.push("cargo:rustc-link-lib=pthread".to_string());
+        } else if let Some(dir) = arg.strip_prefix("-L") {
+            // the bundled .pc files still point at the machine they were built on
+            let dir = Path::new(dir);
+            if dir.is_dir() {
+                directives.push(format!("cargo:rustc-link-search=native={}", dir.display()));
+            }
+        } else if let Some(name) = arg.strip_prefix("-l") {
+            directives.push(link_lib_directive(name, statik, lib_dir));
+        } else if let Some(name) = arg.strip_suffix(".lib") {
+            directives.push(link_lib_directive(name, statik, lib_dir));
+        }
+    }
+
+    directives
+}
+
+fn link_to_libraries(statik: bool, lib_dir: &Path) {
+    // the runtime loader opens the libraries itself
+    if runtime_linking() {
+        return;
+    }
+
+    let pkgconfig_dir = lib_dir.join("pkgconfig");
+    let mut visited = HashSet::new();
+    let mut order = Vec::new();
+
+    for lib in LIBRARIES.iter().filter(|lib| lib.is_enabled()) {
+        let name = format!("lib{}", lib.name);
+        visit_pc_file(&pkgconfig_dir, &name, statik, &mut visited, &mut order);
+    }
+
+    // single-pass static linkers need dependents before their dependencies
+    let args: Vec<String> = order
+        .iter()
+        .rev()
+        .flat_map(|pc| pc.libs.iter().cloned())
+        .collect();
+    let mut directives = link_directives(&args, statik, lib_dir);
+
+    // vcpkg splits lame's decoder into libmpghip-static, which no .pc file mentions
+    if lib_dir.join("libmpghip-static.lib").is_file() {
+        directives.push("cargo:rustc-link-lib=static=libmpghip-static".to_string());
+    }
+
+    // keep the last occurrence of duplicates, like pkg-config does
+    let mut seen = HashSet::new();
+    let mut deduped: Vec<&String> = directives
+        .iter()
+        .rev()
+        .filter(|directive| seen.insert(directive.as_str()))
+        .collect();
+    deduped.reverse();
+
+    for directive in deduped {
+        println!("{}", directive);
+    }
+}
+
+fn runtime_linking() -> bool {
+    env::var("CARGO_FEATURE_RUNTIME_LINKING").is_ok()
+}
+
+fn library_version_major(include_paths: &Vec<PathBuf>, lib: &str) -> Option<u32> {
+    let define = format!("#define LIB{}_VERSION_MAJOR", lib.to_uppercase());
+
+    for header in &["version_major.h", "version.h"] {
+        let path = search_include(include_paths, &format!("lib{}/{}", lib, header));
+        let content = fs::read_to_string(path).unwrap_or_default();
+
+        if let Some(line) = content.lines().find(|line| line.starts_with(&define)) {
+            return line[define.len()..].trim().parse().ok();
+        }
+    }
+
+    None
+}
+
+// The files the linker takes for a static or shared library
+fn library_file_names(name: &str, statik: bool) -> Vec<String> {
+    if statik {
+        vec![format!("lib{}.a", name), format!("{}.lib", name)]
+    } else {
+        vec![
+            format!("lib{}.so", name),
+            format!("lib{}.dylib", name),
+            format!("lib{}.dll.a", name),
+            format!("{}.lib", name),
+        ]
+    }
+}
+
+// The file name the dynamic linker knows a library by, e.g. libavcodec.so.59
+fn shared_library_name(name: &str, major: u32) -> String {
+    match env::var("CARGO_CFG_TARGET_OS").unwrap().as_str() {
+        "macos" | "ios" => format!("lib{}.{}.dylib", name, major),
+        "windows" => format!("{}-{}.dll", name, major),
+        _ => format!("lib{}.so.{}", name, major),
+    }
+}
+
+fn write_runtime_libraries(include_paths: &Vec<PathBuf>, functions: &[String]) {
+    let mut code = String::from("pub static RUNTIME_LIBRARIES: &[RuntimeLibrary] = &[\n");
+
+    for lib in LIBRARIES.iter().filter(|lib| lib.is_enabled()) {
+        let major = library_version_major(include_paths, lib.name)
+            .unwrap_or_else(|| panic!("Unable to find the version of lib{}", lib.name));
+        let file_name = shared_library_name(lib.name, major);
+
+        code.push_str(&format!(
+            "    RuntimeLibrary {{ name: {:?}, file_name: {:?} }},\n",
+            lib.name, file_name
+        ));
+    }
+    code.push_str("];\n\npub static RUNTIME_FUNCTIONS: &[&str] = &[\n");
+
+    for function in functions {
+        code.push_str(&format!("    {:?},\n", function));
+    }
+    code.push_str("];\n");
+
+    // resolves each function from the library handles the Loader opened
+    code.push_str("\nimpl FFmpegFunctions {\n");
+    code.push_str("    unsafe fn from_libraries(libraries: &[Library]) -> FFmpegFunctions {\n");
+    code.push_str("        FFmpegFunctions {\n");
+    code.push_str("            __library: Library::this().into(),\n");
+    for function in functions {
+        code.push_str(&format!(
+            "            {}: symbol(libraries, b\"{}\\0\"),\n",
+            function, function
+        ));
+    }
+    code.push_str("        }\n    }\n}\n");
+
+    fs::write(output().join("runtime_libraries.rs"), code)
+        .expect("Couldn't write runtime_libraries.rs");
+}
+
+fn pregenerated_bindings() -> bool {
+    env::var("CARGO_FEATURE_PREGENERATED_BINDINGS").is_ok()
+}
+
+fn regenerate_bindings() -> bool {
+    println!("cargo:rerun-if-env-changed=FFMPEG_REGENERATE_BINDINGS");
+
+    env::var("FFMPEG_REGENERATE_BINDINGS").is_ok()
+}
+
+// Whether the static inline functions of the headers are exported through
+// extern.c. The pregenerated bindings go without them, so that they need no C
+// compiler, and get the Rust ports runtime-linking uses instead.
+fn static_wrappers() -> bool {
+    !runtime_linking() && !pregenerated_bindings() && !regenerate_bindings()
+}
+
+// bindings/<target>/<components>, e.g. bindings/x86_64-unknown-linux-gnu/avcodec-avformat-avutil-swresample
+fn pregenerated_dir() -> PathBuf {
+    let mut variant = LIBRARIES
+        .iter()
+        .filter(|lib| lib.is_enabled())
+        .map(|lib| lib.name)
+        .collect::<Vec<_>>()
+        .join("-");
+    if runtime_linking() {
+        variant.push_str("-runtime-linking");
+    }
+
+    PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap())
+        .join("bindings")
+        .join(env::var("TARGET").unwrap())
+        .join(variant)
+}
+
+// What bindgen and the probes produce, the rest is read from the headers and
+// libraries as usual
+fn pregenerated_files() -> Vec<&'static str> {
+    let mut files = vec!["bindings.rs", "flags.rs", "probes.txt"];
+    if runtime_linking() {
+        files.push("runtime_libraries.rs");
+    }
+
+    files
+}
+
+fn use_pregenerated_bindings() {
+    let dir = pregenerated_dir();
+    println!("cargo:rerun-if-changed={}", dir.to_string_lossy());
+
+    if !dir.join("bindings.rs").is_file() {
+        panic!(
+            "No pregenerated bindings in {}, build once with FFMPEG_REGENERATE_BINDINGS=1 \
+             and without the pregenerated-bindings feature to create them",
+            dir.display()
+        );
+    }
+
+    for file in pregenerated_files() {
+        fs::copy(dir.join(file), output().join(file))
+            .unwrap_or_else(|e| panic!("Failed to copy pregenerated {}: {}", file, e));
+    }
+}
+
+fn save_pregenerated_bindings() {
+    let dir = pregenerated_dir();
+    fs::create_dir_all(&dir).expect("Failed to create the pregenerated bindings directory");
+
+    for file in pregenerated_files() {
+        fs::copy(output().join(file), dir.join(file))
+            .unwrap_or_else(|e| panic!("Failed to save pregenerated {}: {}", file, e));
+    }
+}
+
+// Same mapping bindgen uses when it picks the target itself
+fn clang_target(target: &str) -> String {
+    if target.starts_with("aarch64-apple-") {
+        target.replacen("aarch64-", "arm64-", 1)
+    } else if target.starts_with("riscv64gc-") {
+        target.replacen("riscv64gc-", "riscv64-", 1)
+    } else {
+        target.to_string()
+    }
+}
+
+// Makes clang parse the headers for the target instead of the host, so that
+// the struct layouts match when cross-compiling.
+fn target_clang_args() -> Vec<String> {
+    let target = env::var("TARGET").unwrap();
+    let mut args = vec![format!("--target={}", clang_target(&target))];
+
+    if target == env::var("HOST").unwrap() {
+        return args;
+    }
+
+    // reuse the sysroot CC/CFLAGS were set up with
+    let compiler = cc::Build::new().get_compiler();
+    let mut compiler_args = compiler
+        .args()
+        .iter()
+        .map(|arg| arg.to_string_lossy().into_owned());
+    let mut has_sysroot = false;
+
+    while let Some(arg) = compiler_args.next() {
+        if arg.starts_with("--sysroot") {
+            has_sysroot = true;
+            args.push(arg);
+        } else if arg == "-isysroot" || arg == "-isystem" {
+            has_sysroot |= arg == "-isysroot";
+            if let Some(dir) = compiler_args.next() {
+                args.push(arg);
+                args.push(dir);
+            }
+        }
+    }
+
+    // a gcc cross toolchain knows its own sysroot, clang doesn't
+    if !has_sysroot && compiler.is_like_gnu() && !compiler.is_like_clang() {
+        let sysroot = Command::new(compiler.path())
+            .arg("-print-sysroot")
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+            .unwrap_or_default();
+
+        if !sysroot.is_empty() {
+            args.push(format!("--sysroot={}", sysroot));
+        }
+    }
+
+    args
+}
+
+// Invocations of the flags! macro for each family with macros in the headers
+fn write_flags(flags: &[String]) {
+    let mut code = String::new();
+
+    for family in FLAG_FAMILIES {
+        let names: Vec<&String> = flags.iter().filter(|name| family.contains(name)).collect();
+        if names.is_empty() {
+            continue;
+        }
+
+        code.push_str(&format!(
+            "flags! {{\n    /// The `{}*` flags\n    {}: {} {{\n",
+            family.prefix, family.name, family.bits
+        ));
+        for name in names {
+            code.push_str(&format!("        {},\n", name));
+        }
+        code.push_str("    }\n}\n");
+
+        for &(ty, field, setter) in family.fields {
+            code.push_str(&format!(
+                "flags_field!({}, {}, {}, {});\n",
+                ty, field, setter, family.name
+            ));
+        }
+    }
+
+    fs::write(output().join("flags.rs"), code).expect("Couldn't write flags.rs");
+}
+
+// Builds the extern.c bindgen wrote for the static inline functions. Its
+// includes are made relative to the include paths first, so the file can be
+// used with the headers from anywhere else.
+fn compile_static_wrappers(include_paths: &Vec<PathBuf>) {
+    let path = output().join("extern.c");
+    let code = fs::read_to_string(&path).expect("Failed to read extern.c");
+
+    let code: Vec<String> = code
+        .lines()
+        .map(|line| {
+            let header = match line
+                .strip_prefix("#include \"")
+                .and_then(|line| line.strip_suffix('"'))
+            {
+                Some(header) => Path::new(header),
+                None => return line.to_string(),
+            };
+            match include_paths
+                .iter()
+                .find_map(|dir| header.strip_prefix(dir).ok())
+            {
+                Some(header) => format!("#include <{}>", header.to_string_lossy()),
+                None => line.to_string(),
+            }
+        })
+        .collect();
+    fs::write(&path, code.join("\n")).expect("Failed to write extern.c");
+
+    cc::Build::new()
+        .file(&path)
+        .includes(include_paths)
+        .warnings(false)
+        .compile("ffmpeg_extern");
+}
+
+// (symbol suffix, generated const), ff_aac_encoder is the aac encoder
+static COMPONENT_KINDS: &[(&str, &str)] = &[
+    ("encoder", "ENABLED_ENCODERS"),
+    ("decoder", "ENABLED_DECODERS"),
+    ("muxer", "ENABLED_MUXERS"),
+    ("demuxer", "ENABLED_DEMUXERS"),
+    ("parser", "ENABLED_PARSERS"),
+    ("protocol", "ENABLED_PROTOCOLS"),
+    ("bsf", "ENABLED_BSFS"),
+];
+
+// The lists configure generates for a source build, e.g. "    &ff_aac_encoder,"
+static COMPONENT_LISTS: &[&str] = &[
+    "libavcodec/codec_list.c",
+    "libavcodec/parser_list.c",
+    "libavcodec/bsf_list.c",
+    "libavformat/muxer_list.c",
+    "libavformat/demuxer_list.c",
+    "libavformat/protocol_list.c",
+];
+
+fn read_be(data: &[u8], width: usize) -> usize {
+    data.iter()
+        .take(width)
+        .fold(0, |value, &byte| (value << 8) | byte as usize)
+}
+
+fn read_le(data: &[u8], width: usize) -> usize {
+    data.iter()
+        .take(width)
+        .rev()
+        .fold(0, |value, &byte| (value << 8) | byte as usize)
+}
+
+fn split_symbol_names(names: &[u8]) -> Vec<String> {
+    names
+        .split(|&byte| byte == 0)
+        .filter(|name| !name.is_empty())
+        .map(|name| String::from_utf8_lossy(name).into_owned())
+        .collect()
+}
+
+// Reads the symbol index of a static archive: the System V/GNU and MSVC "/"
+// member or the BSD "__.SYMDEF" one used on macOS.
+fn archive_symbols(path: &Path) -> io::Result<Vec<String>> {
+    let data = fs::read(path)?;
+    if !data.starts_with(b"!<arch>\n") {
+        return Ok(Vec::new());
+    }
+
+    let mut pos = 8;
+    while pos + 60 <= data.len() {
+        let header = &data[pos..pos + 60];
+        let mut name = String::from_utf8_lossy(&header[..16]).trim_end().to_string();
+        let size: usize = String::from_utf8_lossy(&header[48..58])
+            .trim()
+            .parse()
+            .unwrap_or(0);
+        let mut body = &data[pos + 60..data.len().min(pos + 60 + size)];
+
+        // BSD stores long member names at the start of the body
+        if name.starts_with("#1/") {
+            let len: usize = name[3..].parse().unwrap_or(0).min(body.len());
+            name = String::from_utf8_lossy(&body[..len])
+                .trim_end_matches('\0')
+                .to_string();
+            body = &body[len..];
+        }
+
+        if name == "/" || name == "/SYM64/" {
+            let width = if name == "/" { 4 } else { 8 };
+            let count = read_be(body, width);
+            let names = body.get(width + count * width..).unwrap_or(&[]);
+
+            return Ok(split_symbol_names(names));
+        }
+
+        if name == "__.SYMDEF" || name == "__.SYMDEF SORTED" {
+            let ranlib_size = read_le(body, 4);
+            let strtab = body.get(8 + ranlib_size..).unwrap_or(&[]);
+
+            return Ok(split_symbol_names(strtab));
+        }
+
+        pos += 60 + size + size % 2;
+    }
+
+    Ok(Vec::new())
+}
+
+fn component_symbols(lib_paths: &[PathBuf]) -> Option<Vec<String>> {
+    let lists: Vec<PathBuf> = COMPONENT_LISTS
+        .iter()
+        .map(|list| source().join(list))
+        .filter(|list| list.is_file())
+        .collect();
+
+    if env::var("CARGO_FEATURE_BUILD").is_ok() && !lists.is_empty() {
+        let mut symbols = Vec::new();
+        for list in lists {
+            let content = fs::read_to_string(&list).expect("Failed to read component list");
+            symbols.extend(
+                content
+                    .lines()
+                    .map(|line| line.trim().trim_start_matches('&').trim_end_matches(','))
+                    .filter(|symbol| symbol.starts_with("ff_"))
+                    .map(String::from),
+            );
+        }
+
+        return Some(symbols);
+    }
+
+    let mut symbols = Vec::new();
+    let mut found = false;
+    for lib in &["avcodec", "avformat"] {
+        let archives = [format!("lib{}.a", lib), format!("{}.lib", lib)];
+        let archive = lib_paths
+            .iter()
+            .flat_map(|dir| archives.iter().map(move |archive| dir.join(archive)))
+            .find(|archive| archive.is_file());
+
+        if let Some(archive) = archive {
+            found = true;
+            symbols.extend(
+                archive_symbols(&archive)
+                    .unwrap_or_else(|e| panic!("Failed to read {}: {}", archive.display(), e)),
+            );
+        }
+    }
+
+    if found {
+        Some(symbols)
+    } else {
+        None
+    }
+}
+
+// Lists the codecs, formats, parsers, protocols and bitstream filters
+// compiled into the libraries, as cfgs, links metadata and consts.
+fn detect_components(lib_paths: &[PathBuf]) {
+    let symbols = component_symbols(lib_paths).unwrap_or_else(|| {
+        println!("cargo:warning=No static libavcodec/libavformat found, the ENABLED_* component lists are empty");
+        Vec::new()
+    });
+
+    let mut code = String::new();
+    for &(kind, const_name) in COMPONENT_KINDS {
+        let suffix = format!("_{}", kind);
+        let mut names: Vec<&str> = symbols
+            .iter()
+            .map(|symbol| symbol.trim_start_matches('_'))
+            .filter(|symbol| symbol.starts_with("ff_") && symbol.ends_with(&suffix))
+            .map(|symbol| &symbol[3..symbol.len() - suffix.len()])
+            .filter(|name| !name.is_empty())
+            .collect();
+        names.sort();
+        names.dedup();
+
+        let cfg_name = format!("ffmpeg_{}", kind);
+        let values: Vec<String> = names.iter().map(|name| name.to_string()).collect();
+        println!("{}", check_cfg(&cfg_name, &values));
+        for name in &names {
+            println!(r#"cargo:rustc-cfg={}="{}""#, cfg_name, name);
+        }
+        println!("cargo:{}s={}", kind, names.join(","));
+
+        code.push_str(&format!("pub const {}: &[&str] = &{:?};\n", const_name, names));
+    }
+
+    fs::write(output().join("components.rs"), code).expect("Couldn't write components.rs");
+}
+
+fn find_library(lib_paths: &[PathBuf], name: &str) -> Option<PathBuf> {
+    let names = [
+        format!("lib{}.a", name),
+        format!("{}.lib", name),
+        format!("lib{}.so", name),
+        format!("lib{}.dylib", name),
+    ];
+
+    lib_paths
+        .iter()
+        .flat_map(|dir| names.iter().map(move |name| dir.join(name)))
+        .find(|path| path.is_file())
+}
+
+// Extracts the NUL-terminated string constant containing `marker`
+fn embedded_string(data: &[u8], marker: &[u8]) -> Option<String> {
+    let pos = data
+        .windows(marker.len())
+        .position(|window| window == marker)?;
+    let start = data[..pos]
+        .iter()
+        .rposition(|&byte| byte == 0)
+        .map_or(0, |nul| nul + 1);
+    let end = data[pos..]
+        .iter()
+        .position(|&byte| byte == 0)
+        .map_or(data.len(), |nul| pos + nul);
+
+    Some(String::from_utf8_lossy(&data[start..end]).into_owned())
+}
+
+// The string avutil_license(), avcodec_license(), ... return, e.g.
+// "LGPL version 2.1 or later"
+fn library_license(lib_paths: &[PathBuf], name: &str) -> Option<String> {
+    let prefix = format!("lib{} license: ", name);
+    let data = fs::read(find_library(lib_paths, name)?).ok()?;
+
+    embedded_string(&data, prefix.as_bytes()).map(|license| license[prefix.len()..].to_string())
+}
+
+// The license configure would pick for the build-license-* features
+fn feature_license() -> &'static str {
+    let gpl = env::var("CARGO_FEATURE_BUILD_LICENSE_GPL").is_ok();
+    let version3 = env::var("CARGO_FEATURE_BUILD_LICENSE_VERSION3").is_ok();
+    let nonfree = env::var("CARGO_FEATURE_BUILD_LICENSE_NONFREE").is_ok();
+
+    match (nonfree, gpl, version3) {
+        (true, ..) => "nonfree and unredistributable",
+        (false, true, true) => "GPL version 3 or later",
+        (false, true, false) => "GPL version 2 or later",
+        (false, false, true) => "LGPL version 3 or later",
+        (false, false, false) => "LGPL version 2.1 or later",
+    }
+}
+
+// Higher is more restrictive
+fn license_rank(license: &str) -> u32 {
+    if license.contains("nonfree") {
+        4
+    } else if license.starts_with("GPL") {
+        if license.contains("version 3") {
+            3
+        } else {
+            2
+        }
+    } else if license.contains("version 3") {
+        1
+    } else {
+        0
+    }
+}
+
+// The directories to read the libraries themselves from. pkg-config leaves
+// the system directories out of the link paths, so its libdirs are added.
+fn library_dirs(lib_paths: &[PathBuf]) -> Vec<PathBuf> {
+    let mut dirs = lib_paths.to_vec();
+
+    if env::var("CARGO_FEATURE_BUILD").is_err() && use_pkg_config() {
+        for lib in LIBRARIES.iter().filter(|lib| lib.is_enabled()) {
+            if let Ok(libdir) = pkg_config::get_variable(&format!("lib{}", lib.name), "libdir") {
+                let dir = PathBuf::from(libdir);
+                if !dirs.contains(&dir) {
+                    dirs.push(dir);
+                }
+            }
+        }
+    }
+
+    dirs
+}
+
+// The most restrictive license of the enabled libraries, as they report it.
+// The features only apply to source builds, so they aren't taken as a
+// fallback when none of the libraries can be read.
+fn effective_license(lib_paths: &[PathBuf]) -> String {
+    let dirs = library_dirs(lib_paths);
+
+    LIBRARIES
+        .iter()
+        .filter(|lib| lib.is_enabled())
+        .filter_map(|lib| library_license(&dirs, lib.name))
+        .max_by_key(|license| license_rank(license))
+        .unwrap_or_else(|| {
+            println!(
+                "cargo:warning=None of the FFmpeg libraries found in {:?}, their license is unknown",
+                dirs
+            );
+            "unknown".to_string()
+        })
+}
+
+fn check_nonfree_allowed(license: &str) {
+    println!("cargo:rerun-if-env-changed=FFMPEG_ALLOW_NONFREE");
+
+    if license.contains("nonfree") && env::var("FFMPEG_ALLOW_NONFREE").is_err() {
+        panic!(
+            "FFmpeg is licensed as \"{}\", binaries linking it can't be distributed. \
+             Set FFMPEG_ALLOW_NONFREE=1 to build it anyway",
+            license
+        );
+    }
+}
+
+// Exported for the build scripts of dependent crates as DEP_FFMPEG_*
+fn emit_metadata(include_paths: &[PathBuf], lib_paths: &[PathBuf]) {
+    let join = |paths: &[PathBuf]| {
+        env::join_paths(paths)
+            .expect("Paths can't be joined")
+            .to_string_lossy()
+            .into_owned()
+    };
+    println!("cargo:include={}", join(include_paths));
+    println!("cargo:lib={}", join(lib_paths));
+
+    let components: Vec<&str> = LIBRARIES
+        .iter()
+        .filter(|lib| lib.is_enabled())
+        .map(|lib| lib.name)
+        .collect();
+    println!("cargo:components={}", components.join(","));
+
+    let license = effective_license(lib_paths);
+    check_nonfree_allowed(&license);
+
+    // true or false, unless the license itself is unknown
+    let flag = |set: bool| match &license[..] {
+        "unknown" => "unknown".to_string(),
+        _ => set.to_string(),
+    };
+    println!("cargo:license={}", license);
+    println!("cargo:license_gpl={}", flag(license.starts_with("GPL")));
+    println!("cargo:license_version3={}", flag(license.contains("version 3")));
+    println!("cargo:license_nonfree={}", flag(license.contains("nonfree")));
+
+    fs::write(
+        output().join("license.rs"),
+        format!("pub const FFMPEG_LICENSE: &str = {:?};\n", license),
+    )
+    .expect("Couldn't write license.rs");
+}
+
+// The options libavutil was configured with, as avutil_configuration()
+// returns them
+fn configuration(lib_paths: &[PathBuf]) -> Option<String> {
+    let data = fs::read(find_library(&library_dirs(lib_paths), "avutil")?).ok()?;
+
+    embedded_string(&data, b"--prefix=")
+}
+
+// Generated part of the build_info module
+fn write_build_info(source: &str, lib_paths: &[PathBuf]) {
+    let libraries: Vec<&str> = LIBRARIES
+        .iter()
+        .filter(|lib| lib.is_enabled())
+        .map(|lib| lib.name)
+        .collect();
+    // the libraries check_features reads the version of
+    let versioned: Vec<&str> = libraries
+        .iter()
+        .cloned()
+        .filter(|lib| VERSION_RANGES.iter().any(|range| range.0 == *lib))
+        .collect();
+
+    let mut code = String::new();
+    code.push_str(&format!(
+        "pub const SOURCE: LibrarySource = LibrarySource::{};\n",
+        source
+    ));
+    code.push_str(&format!(
+        "pub const CONFIGURATION: Option<&str> = {:?};\n",
+        configuration(lib_paths)
+    ));
+    code.push_str(&format!("pub const LIBRARIES: &[&str] = &{:?};\n", libraries));
+
+    code.push_str("pub const HEADER_VERSIONS: &[(&str, Version)] = &[\n");
+    for lib in &versioned {
+        code.push_str(&format!(
+            "    ({:?}, Version {{ major: ::{lib}_VERSION_MAJOR, minor: ::{lib}_VERSION_MINOR, micro: ::{lib}_VERSION_MICRO }}),\n",
+            lib,
+            lib = lib.to_uppercase()
+        ));
+    }
+    code.push_str("];\n");
+
+    if runtime_linking() {
+        code.push_str("fn runtime_versions(loader: &::Loader) -> Vec<(&'static str, u32)> {\n");
+    } else {
+        code.push_str("fn runtime_versions() -> Vec<(&'static str, u32)> {\n");
+    }
+    code.push_str("    unsafe {\n        vec![\n");
+    for lib in &versioned {
+        if runtime_linking() {
+            code.push_str(&format!("            ({:?}, loader.{}_version()),\n", lib, lib));
+        } else {
+            code.push_str(&format!("            ({:?}, ::{}_version()),\n", lib, lib));
+        }
+    }
+    code.push_str("        ]\n    }\n}\n");
+
+    fs::write(output().join("build_info.rs"), code).expect("Couldn't write build_info.rs");
+}
+
+fn use_pkg_config() -> bool {
+    println!("cargo:rerun-if-env-changed=FFMPEG_PKG_CONFIG");
+
+    env::var("CARGO_FEATURE_PKG_CONFIG").is_ok() || env::var("FFMPEG_PKG_CONFIG").is_ok()
+}
+
+// pkg-config emits the link search paths and libraries itself, we only need
+// to collect the paths for check_features and bindgen
+fn link_with_pkg_config(statik: bool) -> (Vec<PathBuf>, Vec<PathBuf>) {
+    let mut include_paths = Vec::new();
+    let mut lib_paths = Vec::new();
+
+    for lib in LIBRARIES.iter().filter(|lib| lib.is_enabled()) {
+        let library = pkg_config::Config::new()
+            .statik(statik)
+            .cargo_metadata(!runtime_linking())
+            .probe(&format!("lib{}", lib.name))
+            .unwrap_or_else(|e| panic!("Unable to find lib{} with pkg-config: {}", lib.name, e));
+
+        for dir in library.include_paths {
+            if !include_paths.contains(&dir) {
+                include_paths.push(dir);
+            }
+        }
+        for dir in library.link_paths {
+            if !lib_paths.contains(&dir) {
+                lib_paths.push(dir);
+            }
+        }
+    }
+
+    (include_paths, lib_paths)
+}
+
+// Looks up `NAME_<target>` before `NAME`, the way cc handles CFLAGS
+fn target_env_var(name: &str) -> Option<String> {
+    let target = env::var("TARGET").unwrap();
+    let candidates = [
+        format!("{}_{}", name, target),
+        format!("{}_{}", name, target.replace('-', "_")),
+        name.to_string(),
+    ];
+
+    for var in &candidates {
+        println!("cargo:rerun-if-env-changed={}", var);
+    }
+
+    candidates.iter().filter_map(|var| env::var(var).ok()).next()
+}
+
+fn supported_targets() -> Vec<String> {
+    let builds = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap()).join("builds");
+    let mut targets: Vec<String> = fs::read_dir(builds)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| entry.path().is_dir())
+                .map(|entry| entry.file_name().to_string_lossy().into_owned())
+                .collect()
+        })
+        .unwrap_or_default();
+    targets.sort();
+
+    targets
+}
+
+// Reports everything that's missing at once instead of failing later in
+// bindgen or the linker with an unrelated looking error.
+fn check_prebuilt(include_dir: &Path, lib_dir: &Path, statik: bool) {
+    let mut missing = Vec::new();
+
+    for dir in &[include_dir, lib_dir] {
+        if !dir.is_dir() {
+            missing.push(format!("directory {}", dir.display()));
+        }
+    }
+
+    if missing.is_empty() && !runtime_linking() {
+        for lib in LIBRARIES.iter().filter(|lib| lib.is_enabled()) {
+            let names = library_file_names(lib.name, statik);
+            if !names.iter().any(|name| lib_dir.join(name).is_file()) {
+                let feature = if lib.is_feature {
+                    format!(" (feature `{}`)", lib.name)
+                } else {
+                    String::new()
+                };
+                missing.push(format!("{} in {}{}", names.join(" or "), lib_dir.display(), feature));
+            }
+        }
+    }
+
+    if missing.is_empty() {
+        return;
+    }
+
+    panic!(
+        "The prebuilt FFmpeg for {} doesn't match the enabled features, missing:\n  {}\n\
+         Prebuilt targets: {}\n\
+         Disable the features above, or provide the libraries with FFMPEG_DIR, \
+         the pkg-config feature or the build feature. Static libraries are \
+         looked for with the static feature, shared ones without it.",
+        env::var("TARGET").unwrap(),
+        missing.join("\n  "),
+        supported_targets().join(", ")
+    );
+}
+
+fn link_to_prebuilt(statik: bool) -> (Vec<PathBuf>, Vec<PathBuf>) {
+    let ffmpeg_dir = target_env_var("FFMPEG_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| {
+            env::current_dir()
+                .unwrap()
+                .join("builds")
+                .join(env::var("TARGET").unwrap())
+        });
+    let include_dir = target_env_var("FFMPEG_INCLUDE_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| ffmpeg_dir.join("include"));
+    let lib_dir = target_env_var("FFMPEG_LIB_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| ffmpeg_dir.join("lib"));
+
+    println!("cargo:rerun-if-changed={}", include_dir.to_string_lossy());
+    println!("cargo:rerun-if-changed={}", lib_dir.to_string_lossy());
+    check_prebuilt(&include_dir, &lib_dir, statik);
+
+    println!(
+        "cargo:rustc-link-search=native={}",
+        lib_dir.to_string_lossy()
+    );
+    link_to_libraries(statik, &lib_dir);
+
+    (vec![include_dir], vec![lib_dir])
+}
+
+// The rpath of the shared libraries: their directories, or FFMPEG_RPATH with
+// `:` separated entries like $ORIGIN (@loader_path on Apple targets). An
+// empty FFMPEG_RPATH sets none.
+fn rpaths(lib_paths: &[PathBuf]) -> Vec<String> {
+    match target_env_var("FFMPEG_RPATH") {
+        Some(rpath) => {
+            let apple = env::var("CARGO_CFG_TARGET_VENDOR").unwrap() == "apple";
+            rpath
+                .split(':')
+                .filter(|entry| !entry.is_empty())
+                .map(|entry| {
+                    if apple {
+                        entry.replace("$ORIGIN", "@loader_path")
+                    } else {
+                        entry.to_string()
+                    }
+                })
+                .collect()
+        }
+        None => lib_paths
+            .iter()
+            .map(|dir| dir.to_string_lossy().into_owned())
+            .collect(),
+    }
+}
+
+// Where FFMPEG_STAGE_SHARED_LIBS asks for the shared libraries to be copied:
+// the directory it names, or with 1 the one of the build artifacts
+fn stage_dir() -> Option<PathBuf> {
+    println!("cargo:rerun-if-env-changed=FFMPEG_STAGE_SHARED_LIBS");
+
+    let value = env::var("FFMPEG_STAGE_SHARED_LIBS").ok()?;
+    if value == "1" {
+        // OUT_DIR is target/<profile>/build/ffmpeg4-sys-<hash>/out
+        output().ancestors().nth(3).map(Path::to_path_buf)
+    } else {
+        Some(PathBuf::from(value))
+    }
+}
+
+// Without the static feature: sets the rpath the libraries are found with at
+// runtime and stages them if asked to
+fn link_shared(include_paths: &Vec<PathBuf>, lib_paths: &[PathBuf]) {
+    if env::var("CARGO_CFG_TARGET_FAMILY").unwrap() == "unix" {
+        let rpaths = rpaths(lib_paths);
+        // only applies to the tests and examples of this crate, dependents
+        // get DEP_FFMPEG_RPATH
+        for rpath in &rpaths {
+            println!("cargo:rustc-link-arg=-Wl,-rpath,{}", rpath);
+        }
+        if !rpaths.is_empty() {
+            println!("cargo:rpath={}", rpaths.join(":"));
+        }
+    }
+
+    let stage_dir = match stage_dir() {
+        Some(dir) => dir,
+        None => return,
+    };
+    fs::create_dir_all(&stage_dir)
+        .unwrap_or_else(|e| panic!("Failed to create {}: {}", stage_dir.display(), e));
+
+    for lib in LIBRARIES.iter().filter(|lib| lib.is_enabled()) {
+        let major = library_version_major(include_paths, lib.name)
+            .unwrap_or_else(|| panic!("Unable to find the version of lib{}", lib.name));
+        let name = shared_library_name(lib.name, major);
+
+        // DLLs are installed to the bin directory next to lib
+        let path = lib_paths
+            .iter()
+            .flat_map(|dir| vec![dir.join(&name), dir.with_file_name("bin").join(&name)])
+            .find(|path| path.is_file());
+
+        if let Some(path) = path {
+            fs::copy(&path, stage_dir.join(&name)).unwrap_or_else(|e| {
+                panic!("Failed to copy {} to {}: {}", path.display(), stage_dir.display(), e)
+            });
+        } else {
+            println!(
+                "cargo:warning={} not found, it isn't copied to {}",
+                name,
+                stage_dir.display()
+            );
+        }
+    }
+}
+
+fn main() {
+    let statik = env::var("CARGO_FEATURE_STATIC").is_ok();
+
+    if runtime_linking() && env::var("CARGO_CFG_TARGET_FAMILY").unwrap() != "unix" {
+        panic!("The runtime-linking feature is only supported on unix targets");
+    }
+
+    // before spending time on a source build that would be refused anyway
+    check_nonfree_allowed(feature_license());
+
+    rerun_if_source_build_changed();
+
+    if pregenerated_bindings() && regenerate_bindings() {
+        panic!("FFMPEG_REGENERATE_BINDINGS needs bindgen, build without the pregenerated-bindings feature");
+    }
+
+    if regenerate_scripts() {
+        write_configure_scripts().expect("failed to write the configure scripts");
+    }
+
+    let (source, (include_paths, lib_paths)) = if env::var("CARGO_FEATURE_BUILD").is_ok() {
+        ("SourceBuild", link_to_source_build(statik))
+    } else if use_pkg_config() {
+        ("PkgConfig", link_with_pkg_config(statik))
+    } else {
+        ("Prebuilt", link_to_prebuilt(statik))
+    };
+
+    if !statik && !runtime_linking() {
+        link_shared(&include_paths, &lib_paths);
+    }
+
+    emit_metadata(&include_paths, &lib_paths);
+    write_build_info(source, &lib_paths);
+    detect_components(&lib_paths);
+
+    println!("cargo::rustc-check-cfg=cfg(ffmpeg_static_wrappers)");
+    if static_wrappers() {
+        println!("cargo:rustc-cfg=ffmpeg_static_wrappers");
+    }
+
+    if pregenerated_bindings() {
+        use_pregenerated_bindings();
+    }
+
+    let api_macros = api_macros(&include_paths);
+    let cfgs = check_features(include_paths.clone(), &api_macros);
+    for cfg in &cfgs {
+        println!("{}", cfg);
+    }
+
+    if pregenerated_bindings() {
+        return;
+    }
+
+    // For debugging purpose only.
+    let tmp = std::env::temp_dir();
+    let mut f = File::create(tmp.join("ffmpeg4.build")).expect("Filed to create ffmpeg4.build");
     let tool = cc::Build::new().get_compiler();
     write!(f, "{}", tool.path().to_string_lossy().into_owned()).expect("failed to write cmd");
     for arg in tool.args() {
@@ -901,10 +2018,15 @@ fn main() {
         .iter()
         .map(|include| format!("-I{}", include.to_string_lossy()));
 
+    let callbacks = Callbacks::default();
+    let functions = callbacks.functions.clone();
+    let flags = callbacks.flags.clone();
+
     // The bindgen::Builder is the main entry point
     // to bindgen, and lets you build up options for
     // the resulting bindings.
     let mut builder = bindgen::Builder::default()
+        .clang_args(target_clang_args())
         .clang_args(clang_includes)
         .ctypes_prefix("libc")
         // https://github.com/rust-lang/rust-bindgen/issues/550
@@ -912,96 +2034,31 @@ fn main() {
         // Issue on aligned and packed struct. Related to:
         // https://github.com/rust-lang/rust-bindgen/issues/1538
         .opaque_type("__mingw_ldbl_type_t")
-        // these are never part of ffmpeg API
-        .blocklist_function("_.*")
-        // Rust doesn't support long double, and bindgen can't skip it
-        // https://github.com/rust-lang/rust-bindgen/issues/1549
-        .blocklist_function("acoshl")
-        .blocklist_function("acosl")
-        .blocklist_function("asinhl")
-        .blocklist_function("asinl")
-        .blocklist_function("atan2l")
-        .blocklist_function("atanhl")
-        .blocklist_function("atanl")
-        .blocklist_function("cbrtl")
-        .blocklist_function("ceill")
-        .blocklist_function("copysignl")
-        .blocklist_function("coshl")
-        .blocklist_function("cosl")
-        .blocklist_function("dreml")
-        .blocklist_function("ecvt_r")
-        .blocklist_function("erfcl")
-        .blocklist_function("erfl")
-        .blocklist_function("exp2l")
-        .blocklist_function("expl")
-        .blocklist_function("expm1l")
-        .blocklist_function("fabsl")
-        .blocklist_function("fcvt_r")
-        .blocklist_function("fdiml")
-        .blocklist_function("finitel")
-        .blocklist_function("floorl")
-        .blocklist_function("fmal")
-        .blocklist_function("fmaxl")
-        .blocklist_function("fminl")
-        .blocklist_function("fmodl")
-        .blocklist_function("frexpl")
-        .blocklist_function("gammal")
-        .blocklist_function("hypotl")
-        .blocklist_function("ilogbl")
-        .blocklist_function("isinfl")
-        .blocklist_function("isnanl")
-        .blocklist_function("j0l")
-        .blocklist_function("j1l")
-        .blocklist_function("jnl")
-        .blocklist_function("ldexpl")
-        .blocklist_function("lgammal")
-        .blocklist_function("lgammal_r")
-        .blocklist_function("llrintl")
-        .blocklist_function("llroundl")
-        .blocklist_function("log10l")
-        .blocklist_function("log1pl")
-        .blocklist_function("log2l")
-        .blocklist_function("logbl")
-        .blocklist_function("logl")
-        .blocklist_function("lrintl")
-        .blocklist_function("lroundl")
-        .blocklist_function("modfl")
-        .blocklist_function("nanl")
-        .blocklist_function("nearbyintl")
-        .blocklist_function("nextafterl")
-        .blocklist_function("nexttoward")
-        .blocklist_function("nexttowardf")
-        .blocklist_function("nexttowardl")
-        .blocklist_function("powl")
-        .blocklist_function("qecvt")
-        .blocklist_function("qecvt_r")
-        .blocklist_function("qfcvt")
-        .blocklist_function("qfcvt_r")
-        .blocklist_function("qgcvt")
-        .blocklist_function("remainderl")
-        .blocklist_function("remquol")
-        .blocklist_function("rintl")
-        .blocklist_function("roundl")
-        .blocklist_function("scalbl")
-        .blocklist_function("scalblnl")
-        .blocklist_function("scalbnl")
-        .blocklist_function("significandl")
-        .blocklist_function("sinhl")
-        .blocklist_function("sinl")
-        .blocklist_function("sqrtl")
-        .blocklist_function("strtold")
-        .blocklist_function("tanhl")
-        .blocklist_function("tanl")
-        .blocklist_function("tgammal")
-        .blocklist_function("truncl")
-        .blocklist_function("y0l")
-        .blocklist_function("y1l")
-        .blocklist_function("ynl")
-        .rustified_enum("*")
+        // C enums can hold values newer libraries add, so no Rust enums
+        .newtype_enum("*")
         .prepend_enum_name(false)
         .derive_eq(true)
         .size_t_is_usize(true)
-        .parse_callbacks(Box::new(Callbacks));
+        // docs from the headers, made rustdoc friendly by process_comment
+        .generate_comments(true)
+        .clang_arg("-fretain-comments-from-system-headers")
+        .parse_callbacks(Box::new(callbacks));
+
+    // only the items of the enabled libraries, not what the system headers declare
+    for library in LIBRARIES.iter().filter(|library| library.is_enabled()) {
+        for item in library.items {
+            builder = builder.allowlist_item(item);
+        }
+    }
+
+    if runtime_linking() {
+        builder = builder.dynamic_library_name("FFmpegFunctions");
+    } else if static_wrappers() {
+        // export the static inline functions of the headers through extern.c
+        builder = builder
+            .wrap_static_fns(true)
+            .wrap_static_fns_path(output().join("extern"));
+    }
 
     // The input headers we would like to generate
     // bindings for.
@@ -1111,4 +2168,29 @@ fn main() {
     bindings
         .write_to_file(output().join("bindings.rs"))
         .expect("Couldn't write bindings!");
+
+    let mut flags = flags.lock().unwrap().clone();
+    flags.sort();
+    flags.dedup();
+    write_flags(&flags);
+
+    if runtime_linking() {
+        // only the functions of the enabled libraries get bindings
+        let mut functions = functions.lock().unwrap().clone();
+        functions.retain(|function| {
+            LIBRARIES
+                .iter()
+                .any(|library| library.is_enabled() && library.declares(function))
+        });
+        functions.sort();
+        functions.dedup();
+
+        write_runtime_libraries(&include_paths, &functions);
+    } else if static_wrappers() {
+        compile_static_wrappers(&include_paths);
+    }
+
+    if regenerate_bindings() {
+        save_pregenerated_bindings();
+    }
 }
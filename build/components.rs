@@ -0,0 +1,352 @@
+// Component profiles and external libraries of the source build, shared by
+// build.rs and the tests.
+
+use std::io;
+use std::path::Path;
+use std::process::Command;
+
+// The profiles in this file, next to Cargo.toml
+pub static PROFILES_FILE: &str = "ffmpeg_profiles.txt";
+
+// Cargo feature -> configure's name for the external library
+pub static EXTERNAL_LIBRARIES: &[(&str, &str)] = &[
+    // ssl
+    ("build-lib-gnutls", "gnutls"),
+    ("build-lib-openssl", "openssl"),
+    // filters
+    ("build-lib-fontconfig", "fontconfig"),
+    ("build-lib-frei0r", "frei0r"),
+    ("build-lib-ladspa", "ladspa"),
+    ("build-lib-ass", "libass"),
+    ("build-lib-freetype", "libfreetype"),
+    ("build-lib-fribidi", "libfribidi"),
+    ("build-lib-opencv", "libopencv"),
+    // encoders/decoders
+    ("build-lib-celt", "libcelt"),
+    ("build-lib-fdk-aac", "libfdk-aac"),
+    ("build-lib-gsm", "libgsm"),
+    ("build-lib-ilbc", "libilbc"),
+    ("build-lib-kvazaar", "libkvazaar"),
+    ("build-lib-mp3lame", "libmp3lame"),
+    ("build-lib-opencore-amrnb", "libopencore-amrnb"),
+    ("build-lib-opencore-amrwb", "libopencore-amrwb"),
+    ("build-lib-openh264", "libopenh264"),
+    ("build-lib-openjpeg", "libopenjpeg"),
+    ("build-lib-opus", "libopus"),
+    ("build-lib-shine", "libshine"),
+    ("build-lib-snappy", "libsnappy"),
+    ("build-lib-speex", "libspeex"),
+    ("build-lib-theora", "libtheora"),
+    ("build-lib-twolame", "libtwolame"),
+    ("build-lib-vo-amrwbenc", "libvo-amrwbenc"),
+    ("build-lib-vorbis", "libvorbis"),
+    ("build-lib-vpx", "libvpx"),
+    ("build-lib-wavpack", "libwavpack"),
+    ("build-lib-webp", "libwebp"),
+    ("build-lib-x264", "libx264"),
+    ("build-lib-x265", "libx265"),
+    ("build-lib-xvid", "libxvid"),
+    // misc
+    ("build-nvenc", "nvenc"),
+    ("build-sdl2", "sdl2"),
+    ("build-zlib", "zlib"),
+    // protocols
+    ("build-lib-smbclient", "libsmbclient"),
+    ("build-lib-ssh", "libssh"),
+];
+
+// The env vars selecting components on top of --disable-everything, and
+// their kind
+pub static COMPONENT_VARS: &[(&str, &str)] = &[
+    ("FFMPEG_ENABLE_DECODERS", "decoder"),
+    ("FFMPEG_ENABLE_ENCODERS", "encoder"),
+    ("FFMPEG_ENABLE_MUXERS", "muxer"),
+    ("FFMPEG_ENABLE_DEMUXERS", "demuxer"),
+    ("FFMPEG_ENABLE_PARSERS", "parser"),
+    ("FFMPEG_ENABLE_PROTOCOLS", "protocol"),
+];
+
+// The generated configure scripts and their extra arguments
+pub static SCRIPTS: &[(&str, &[&str])] = &[
+    ("ffmpeg_configure_tiny.sh", &["--enable-audiotoolbox"]),
+    ("ffmpeg_configure_tiny_linux.sh", &[]),
+];
+
+// The features the scripts are generated for
+static SCRIPT_FEATURES: &[&str] = &["tiny", "common-audio", "build-lib-mp3lame"];
+
+// What the scripts pass to configure before the components
+static SCRIPT_ARGS: &[&str] = &[
+    "--prefix=build_output",
+    "--enable-debug",
+    "--enable-runtime-cpudetect",
+    "--enable-asm",
+    "--enable-x86asm",
+    "--enable-static",
+    "--disable-shared",
+    "--enable-pic",
+    "--disable-programs",
+    "--disable-gpl",
+    "--disable-version3",
+    "--disable-nonfree",
+    "--enable-avcodec",
+    "--disable-avdevice",
+    "--disable-avfilter",
+    "--enable-avformat",
+    "--disable-postproc",
+    "--enable-swresample",
+    "--disable-swscale",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Profile {
+    // the Cargo feature enabling it
+    pub name: String,
+    // (kind, name), e.g. ("decoder", "flac")
+    pub components: Vec<(String, String)>,
+}
+
+pub fn parse_profiles(manifest: &str) -> io::Result<Vec<Profile>> {
+    let mut profiles: Vec<Profile> = Vec::new();
+
+    for (number, line) in manifest.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let error = |message: &str| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}:{}: {}: {}", PROFILES_FILE, number + 1, message, line),
+            )
+        };
+
+        if line.starts_with('[') && line.ends_with(']') {
+            profiles.push(Profile {
+                name: line[1..line.len() - 1].to_string(),
+                components: Vec::new(),
+            });
+            continue;
+        }
+
+        let mut words = line.split_whitespace();
+        let component = match (words.next(), words.next(), words.next()) {
+            (Some(kind), Some(name), None) => (kind.to_string(), name.to_string()),
+            _ => return Err(error("expected \"<kind> <name>\"")),
+        };
+        match profiles.last_mut() {
+            Some(profile) => profile.components.push(component),
+            None => return Err(error("component outside of a profile")),
+        }
+    }
+
+    Ok(profiles)
+}
+
+// build-lib-fdk-aac -> CARGO_FEATURE_BUILD_LIB_FDK_AAC
+pub fn feature_var(feature: &str) -> String {
+    format!("CARGO_FEATURE_{}", feature.to_uppercase().replace('-', "_"))
+}
+
+// "aac, flac mp3" -> ["aac", "flac", "mp3"]
+pub fn parse_list(value: &str) -> Vec<String> {
+    value
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|name| !name.is_empty())
+        .map(|name| name.to_string())
+        .collect()
+}
+
+// Splits FFMPEG_EXTRA_CONFIGURE_ARGS like sh does, without expansions:
+// `--extra-cflags="-I/opt/x -DFOO"` is one argument
+pub fn split_args(value: &str) -> io::Result<Vec<String>> {
+    let mut args = Vec::new();
+    // None between arguments
+    let mut arg: Option<String> = None;
+    let mut chars = value.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {
+                args.extend(arg.take());
+                continue;
+            }
+            '\'' => {
+                let arg = arg.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => arg.push(c),
+                        None => return Err(unterminated(value, "'")),
+                    }
+                }
+            }
+            '"' => {
+                let arg = arg.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        // only these are escaped inside double quotes
+                        Some('\\') => match chars.next() {
+                            Some(c) if "\"\\$`".contains(c) => arg.push(c),
+                            Some(c) => {
+                                arg.push('\\');
+                                arg.push(c);
+                            }
+                            None => return Err(unterminated(value, "\"")),
+                        },
+                        Some(c) => arg.push(c),
+                        None => return Err(unterminated(value, "\"")),
+                    }
+                }
+            }
+            '\\' => {
+                if let Some(c) = chars.next() {
+                    arg.get_or_insert_with(String::new).push(c);
+                }
+            }
+            c => arg.get_or_insert_with(String::new).push(c),
+        }
+    }
+    args.extend(arg);
+
+    Ok(args)
+}
+
+fn unterminated(value: &str, quote: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("unterminated {} in FFMPEG_EXTRA_CONFIGURE_ARGS: {}", quote, value),
+    )
+}
+
+// Whether configure knows the component, a pattern like pcm_* has to match one
+pub fn is_known(name: &str, known: &[String]) -> bool {
+    known.iter().any(|known| matches_pattern(name, known))
+}
+
+// configure's patterns, with * for any number of characters
+fn matches_pattern(pattern: &str, name: &str) -> bool {
+    match pattern.find('*') {
+        None => pattern == name,
+        Some(star) => {
+            let (prefix, rest) = (&pattern[..star], &pattern[star + 1..]);
+            name.starts_with(prefix)
+                && (prefix.len()..=name.len())
+                    .any(|start| matches_pattern(rest, &name[start..]))
+        }
+    }
+}
+
+// The components listed in FFMPEG_ENABLE_*, looked up with `lookup`, as
+// (kind, names) checked against what configure knows
+pub fn selected_components<F: Fn(&str) -> Option<String>>(
+    configure_path: &Path,
+    lookup: F,
+) -> io::Result<Vec<(&'static str, Vec<String>)>> {
+    let mut selected = Vec::new();
+
+    for &(var, kind) in COMPONENT_VARS {
+        let names = match lookup(var) {
+            Some(value) => parse_list(&value),
+            None => continue,
+        };
+
+        if !names.is_empty() {
+            let output = Command::new(configure_path)
+                .arg(format!("--list-{}s", kind))
+                // the build directory doesn't exist yet on a clean build
+                .current_dir(configure_path.parent().unwrap_or(configure_path))
+                .output()?;
+            if !output.status.success() {
+                return Err(io::Error::other(format!("configure --list-{}s failed", kind)));
+            }
+
+            let known = parse_list(&String::from_utf8_lossy(&output.stdout));
+            let unknown: Vec<&str> = names
+                .iter()
+                .filter(|name| !is_known(name, &known))
+                .map(|name| &name[..])
+                .collect();
+            if !unknown.is_empty() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("{} lists unknown {}s: {}", var, kind, unknown.join(", ")),
+                ));
+            }
+        }
+
+        selected.push((kind, names));
+    }
+
+    Ok(selected)
+}
+
+// The configure arguments for the profiles and external libraries of the
+// enabled features, and the components selected as (kind, names)
+pub fn configure_args<F: Fn(&str) -> bool>(
+    profiles: &[Profile],
+    enabled: F,
+    selected: &[(&str, Vec<String>)],
+) -> Vec<String> {
+    let mut args = Vec::new();
+
+    // the profiles and selected components only add to what tiny leaves
+    if enabled("tiny") || !selected.is_empty() {
+        args.push("--disable-everything".to_string());
+    }
+    for profile in profiles.iter().filter(|profile| enabled(&profile.name)) {
+        for (kind, name) in &profile.components {
+            args.push(format!("--enable-{}={}", kind, name));
+        }
+    }
+    for &(kind, ref names) in selected {
+        for name in names {
+            args.push(format!("--enable-{}={}", kind, name));
+        }
+    }
+
+    for &(feature, name) in EXTERNAL_LIBRARIES {
+        let switch = if enabled(feature) { "enable" } else { "disable" };
+        args.push(format!("--{}-{}", switch, name));
+    }
+
+    args
+}
+
+pub fn configure_script(profiles: &[Profile], extra_args: &[&str]) -> String {
+    let mut script = format!(
+        "#!/bin/sh
+
+# Generated from {} by build.rs, don't edit
+
+if [ -z $LAME_LOCATION ]; then
+\techo \"No libmp3lame location set\"
+\texit 1
+fi
+
+export CFLAGS=\"-I$LAME_LOCATION/include\"
+export LDFLAGS=\"-L$LAME_LOCATION/lib\"
+
+./configure",
+        PROFILES_FILE
+    );
+
+    let args = SCRIPT_ARGS
+        .iter()
+        .chain(extra_args)
+        .map(|arg| arg.to_string())
+        .chain(configure_args(
+            profiles,
+            |feature| SCRIPT_FEATURES.contains(&feature),
+            &[],
+        ));
+    for arg in args {
+        script.push_str(" \\\n");
+        script.push_str(&arg);
+    }
+    script.push('\n');
+
+    script
+}
@@ -0,0 +1,239 @@
+// Turns the Doxygen markup of the header comments into rustdoc, shared by
+// build.rs and the tests.
+
+// Doxygen commands dropped with the rest of their line
+static DROPPED: &[&str] = &[
+    "addtogroup",
+    "anchor",
+    "author",
+    "def",
+    "defgroup",
+    "file",
+    "ingroup",
+    "{",
+    "}",
+];
+
+// Doxygen commands turned into a label for the rest of their line
+static LABELS: &[(&str, &str)] = &[
+    ("attention", "Attention: "),
+    ("brief", ""),
+    ("deprecated", "Deprecated: "),
+    ("li", "- "),
+    ("note", "Note: "),
+    ("par", ""),
+    ("return", "Returns "),
+    ("returns", "Returns "),
+    ("sa", "See also: "),
+    ("see", "See also: "),
+    ("since", "Since "),
+    ("todo", "TODO: "),
+    ("warning", "Warning: "),
+];
+
+// The doxygen command at the start of `line` and the text after it
+fn parse_command(line: &str) -> Option<(&str, &str)> {
+    let rest = line
+        .strip_prefix('@')
+        .or_else(|| line.strip_prefix('\\'))?;
+    let end = rest
+        .find(|c: char| c.is_whitespace())
+        .unwrap_or(rest.len());
+
+    // "@note," and "@warning:" are used too
+    let command = rest[..end].trim_end_matches(&[',', ':'][..]);
+
+    Some((command, rest[end..].trim_start()))
+}
+
+// @ref, @p, ... inside a line
+fn inline(text: &str) -> String {
+    let mut words = Vec::new();
+    // the markdown to wrap the next word in, and the parentheses before it
+    let mut wrap_next: Option<(&str, &str)> = None;
+
+    // the javadoc style {@code name}
+    let mut text = text.to_string();
+    while let Some(start) = text.find("{@code ") {
+        let end = match text[start..].find('}') {
+            Some(end) => start + end,
+            None => break,
+        };
+        let code = format!("`{}`", &text[start + "{@code ".len()..end]);
+        text.replace_range(start..=end, &code);
+    }
+
+    for word in text.split(' ') {
+        if let (Some((wrap, open)), false) = (wrap_next, word.is_empty()) {
+            // leave trailing punctuation and unbalanced parentheses outside
+            let mut end = word.len();
+            while let Some(c) = word[..end].chars().last() {
+                let unbalanced =
+                    word[..end].matches('(').count() < word[..end].matches(')').count();
+                if ".,;:".contains(c) || (c == ')' && unbalanced) {
+                    end -= 1;
+                } else {
+                    break;
+                }
+            }
+            words.push(format!("{}{}{}{}{}", open, wrap, &word[..end], wrap, &word[end..]));
+            wrap_next = None;
+            continue;
+        }
+
+        let start = word.len() - word.trim_start_matches('(').len();
+        let (open, command) = word.split_at(start);
+        let wrap = match parse_command(command) {
+            Some(("ref", "")) | Some(("p", "")) | Some(("a", "")) | Some(("c", "")) => "`",
+            Some(("e", "")) | Some(("em", "")) => "*",
+            Some(("b", "")) => "**",
+            Some(("see", "")) | Some(("sa", "")) => {
+                words.push(format!("{}see", open));
+                continue;
+            }
+            _ => {
+                words.push(word.to_string());
+                continue;
+            }
+        };
+        wrap_next = Some((wrap, open));
+    }
+
+    words.join(" ")
+}
+
+// Escapes what rustdoc would take for links or HTML outside of code spans
+fn escape_markdown(text: &str) -> String {
+    text.split('`')
+        .enumerate()
+        .map(|(i, part)| {
+            if i % 2 == 1 {
+                return part.to_string();
+            }
+            part.replace('[', "\\[")
+                .replace(']', "\\]")
+                .replace('<', "\\<")
+        })
+        .collect::<Vec<_>>()
+        .join("`")
+}
+
+// The name or value a @param or @retval documents and the description after
+// it, the value can be quoted: @retval "another negative error code" ...
+fn split_term(rest: &str) -> (&str, &str) {
+    let end = match rest.strip_prefix('"').and_then(|quoted| quoted.find('"')) {
+        Some(quote) => quote + 2,
+        None => rest.find(' ').unwrap_or(rest.len()),
+    };
+
+    (rest[..end].trim_matches('"'), rest[end..].trim_start())
+}
+
+fn line_to_rustdoc(line: &str) -> Option<String> {
+    let trimmed = line.trim_start();
+    let indent = &line[..line.len() - trimmed.len()];
+
+    let text = match parse_command(trimmed) {
+        Some((command, _)) if DROPPED.contains(&command) => return None,
+        Some((command, rest)) if command == "param" || command.starts_with("param[") => {
+            let (name, description) = split_term(rest);
+            format!("- `{}`: {}", name, description)
+        }
+        Some(("name", title)) => format!("## {}", title),
+        // the title follows the section's name, untitled sections are dropped
+        Some(("section", rest)) => format!("## {}", rest.split_once(' ')?.1),
+        Some(("subsection", rest)) => format!("### {}", rest.split_once(' ')?.1),
+        Some(("retval", rest)) => {
+            let (value, description) = split_term(rest);
+            format!("- Returns `{}`: {}", value, description)
+        }
+        Some((command, rest)) => match LABELS.iter().find(|label| label.0 == command) {
+            Some(&(_, label)) => format!("{}{}", label, rest),
+            None => trimmed.to_string(),
+        },
+        None => trimmed.to_string(),
+    };
+
+    Some(format!("{}{}", indent, escape_markdown(&inline(&text))))
+}
+
+// Turns the doxygen markup of the FFmpeg headers into rustdoc markdown. Code,
+// whether marked with @code or only indented, is fenced as text so rustdoc
+// doesn't run it as a doctest.
+pub fn to_rustdoc(comment: &str) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    // inside @code ... @endcode
+    let mut in_code = false;
+    // inside an indented block
+    let mut in_indented = false;
+    let mut previous_blank = true;
+
+    // the < of the ///< comments documenting the member before them
+    let comment = comment.strip_prefix('<').unwrap_or(comment);
+
+    for line in comment.lines() {
+        // bindgen leaves the space after the * of each line
+        let line = line.strip_prefix(' ').unwrap_or(line).replace('\t', "    ");
+        let trimmed = line.trim_start();
+        let indent = line.len() - trimmed.len();
+
+        if in_code {
+            if let Some(("endcode", _)) | Some(("endverbatim", _)) = parse_command(trimmed) {
+                lines.push("```".to_string());
+                in_code = false;
+            } else {
+                lines.push(line.clone());
+            }
+            continue;
+        }
+
+        if in_indented {
+            if trimmed.is_empty() || indent >= 4 {
+                lines.push(line.get(4..).unwrap_or("").to_string());
+                continue;
+            }
+            lines.push("```".to_string());
+            in_indented = false;
+        }
+
+        match parse_command(trimmed) {
+            Some((command, _))
+                if command == "code" || command.starts_with("code{") || command == "verbatim" =>
+            {
+                lines.push("```text".to_string());
+                in_code = true;
+                continue;
+            }
+            _ => {}
+        }
+
+        if indent >= 4 && previous_blank && !trimmed.is_empty() {
+            lines.push("```text".to_string());
+            lines.push(line[4..].to_string());
+            in_indented = true;
+            continue;
+        }
+
+        previous_blank = trimmed.is_empty();
+        if let Some(line) = line_to_rustdoc(&line) {
+            lines.push(line);
+        }
+    }
+
+    if in_code || in_indented {
+        lines.push("```".to_string());
+    }
+
+    // left by dropped commands like @defgroup
+    let start = lines.iter().position(|line| !line.trim().is_empty()).unwrap_or(lines.len());
+
+    // keep the space rustdoc expects after ///
+    lines[start..]
+        .iter()
+        .map(|line| match line.trim_end() {
+            "" => String::new(),
+            line => format!(" {}", line),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
@@ -1,5 +1,7 @@
 #!/bin/sh
 
+# Generated from ffmpeg_profiles.txt by build.rs, don't edit
+
 if [ -z $LAME_LOCATION ]; then
 	echo "No libmp3lame location set"
 	exit 1
@@ -28,9 +30,8 @@ export LDFLAGS="-L$LAME_LOCATION/lib"
 --disable-postproc \
 --enable-swresample \
 --disable-swscale \
---disable-everything \
---enable-libmp3lame \
 --enable-audiotoolbox \
+--disable-everything \
 --enable-protocol=file \
 --enable-protocol=pipe \
 --enable-demuxer=pcm_f64be \
@@ -122,6 +123,7 @@ export LDFLAGS="-L$LAME_LOCATION/lib"
 --disable-libgsm \
 --disable-libilbc \
 --disable-libkvazaar \
+--enable-libmp3lame \
 --disable-libopencore-amrnb \
 --disable-libopencore-amrwb \
 --disable-libopenh264 \
@@ -135,12 +137,13 @@ export LDFLAGS="-L$LAME_LOCATION/lib"
 --disable-libvo-amrwbenc \
 --disable-libvorbis \
 --disable-libvpx \
+--disable-libwavpack \
 --disable-libwebp \
 --disable-libx264 \
 --disable-libx265 \
 --disable-libxvid \
 --disable-nvenc \
 --disable-sdl2 \
+--disable-zlib \
 --disable-libsmbclient \
---disable-libssh \
---disable-zlib
+--disable-libssh
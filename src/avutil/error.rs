@@ -1,4 +1,6 @@
-use libc::{c_char, c_int, size_t};
+use libc::c_int;
+#[cfg(not(ffmpeg_static_wrappers))]
+use libc::{c_char, size_t};
 
 #[inline(always)]
 pub fn AVERROR(e: c_int) -> c_int {
@@ -43,17 +45,31 @@ pub const AVERROR_HTTP_NOT_FOUND: c_int = FFERRTAG!(0xF8, b'4', b'0', b'4');
 pub const AVERROR_HTTP_OTHER_4XX: c_int = FFERRTAG!(0xF8, b'4', b'X', b'X');
 pub const AVERROR_HTTP_SERVER_ERROR: c_int = FFERRTAG!(0xF8, b'5', b'X', b'X');
 
+// Otherwise av_make_error_string is exported from the headers by the extern.c
+// shim, and av_strerror is part of the bindings
+#[cfg(all(not(ffmpeg_static_wrappers), not(feature = "runtime-linking")))]
 #[inline(always)]
 pub unsafe fn av_make_error_string(
     errbuf: *mut c_char,
     errbuf_size: size_t,
     errnum: c_int,
 ) -> *mut c_char {
-    av_strerror(errnum, errbuf, errbuf_size);
+    ::av_strerror(errnum, errbuf, errbuf_size);
 
     errbuf
 }
 
-extern "C" {
-    pub fn av_strerror(errnum: c_int, errbuf: *mut c_char, errbuf_size: size_t) -> c_int;
+#[cfg(feature = "runtime-linking")]
+impl ::Loader {
+    #[inline(always)]
+    pub unsafe fn av_make_error_string(
+        &self,
+        errbuf: *mut c_char,
+        errbuf_size: size_t,
+        errnum: c_int,
+    ) -> *mut c_char {
+        self.av_strerror(errnum, errbuf, errbuf_size);
+
+        errbuf
+    }
 }
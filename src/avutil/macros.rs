@@ -17,3 +17,122 @@ macro_rules! MKTAG {
 		(($d as isize) << 24)
 	)
 }
+
+// A bitflags-like newtype over the raw flag consts of one C flag family
+macro_rules! flags {
+	($(#[$attr:meta])* $name:ident: $bits:ty { $($flag:ident,)* }) => (
+		$(#[$attr])*
+		#[repr(transparent)]
+		#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+		pub struct $name(pub $bits);
+
+		impl $name {
+			$(pub const $flag: $name = $name($flag as $bits);)*
+
+			pub const fn empty() -> $name {
+				$name(0)
+			}
+
+			pub const fn bits(self) -> $bits {
+				self.0
+			}
+
+			pub const fn from_bits_retain(bits: $bits) -> $name {
+				$name(bits)
+			}
+
+			pub const fn is_empty(self) -> bool {
+				self.0 == 0
+			}
+
+			pub const fn contains(self, other: $name) -> bool {
+				self.0 & other.0 == other.0
+			}
+
+			pub const fn intersects(self, other: $name) -> bool {
+				self.0 & other.0 != 0
+			}
+
+			pub fn insert(&mut self, other: $name) {
+				self.0 |= other.0;
+			}
+
+			pub fn remove(&mut self, other: $name) {
+				self.0 &= !other.0;
+			}
+		}
+
+		impl ::std::ops::BitOr for $name {
+			type Output = $name;
+
+			fn bitor(self, other: $name) -> $name {
+				$name(self.0 | other.0)
+			}
+		}
+
+		impl ::std::ops::BitOrAssign for $name {
+			fn bitor_assign(&mut self, other: $name) {
+				self.0 |= other.0;
+			}
+		}
+
+		impl ::std::ops::BitAnd for $name {
+			type Output = $name;
+
+			fn bitand(self, other: $name) -> $name {
+				$name(self.0 & other.0)
+			}
+		}
+
+		impl ::std::ops::BitAndAssign for $name {
+			fn bitand_assign(&mut self, other: $name) {
+				self.0 &= other.0;
+			}
+		}
+
+		impl ::std::ops::BitXor for $name {
+			type Output = $name;
+
+			fn bitxor(self, other: $name) -> $name {
+				$name(self.0 ^ other.0)
+			}
+		}
+
+		impl ::std::ops::BitXorAssign for $name {
+			fn bitxor_assign(&mut self, other: $name) {
+				self.0 ^= other.0;
+			}
+		}
+
+		impl ::std::ops::Sub for $name {
+			type Output = $name;
+
+			fn sub(self, other: $name) -> $name {
+				$name(self.0 & !other.0)
+			}
+		}
+
+		impl ::std::ops::Not for $name {
+			type Output = $name;
+
+			fn not(self) -> $name {
+				$name(!self.0)
+			}
+		}
+	)
+}
+
+// Typed accessors for a struct field holding one of the flag types
+macro_rules! flags_field {
+	($ty:ident, $field:ident, $setter:ident, $flags:ident) => (
+		impl $ty {
+			pub fn $field(&self) -> $flags {
+				$flags(self.$field)
+			}
+
+			pub fn $setter(&mut self, flags: $flags) {
+				self.$field = flags.0;
+			}
+		}
+	)
+}
@@ -7,7 +7,10 @@ pub use self::error::*;
 mod util;
 pub use self::util::*;
 
+// Otherwise these are exported from the headers by the extern.c shim
+#[cfg(not(ffmpeg_static_wrappers))]
 mod rational;
+#[cfg(not(ffmpeg_static_wrappers))]
 pub use self::rational::*;
 
 mod pixfmt;
@@ -0,0 +1,113 @@
+//! What the crate was built against, and a check that the libraries used at
+//! runtime match it.
+
+use std::fmt;
+
+pub use {
+    ENABLED_BSFS, ENABLED_DECODERS, ENABLED_DEMUXERS, ENABLED_ENCODERS, ENABLED_MUXERS,
+    ENABLED_PARSERS, ENABLED_PROTOCOLS, FFMPEG_LICENSE,
+};
+
+/// Where the libraries the crate was built against came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LibrarySource {
+    /// The prebuilt bundle in `builds/<target>` or `FFMPEG_DIR`.
+    Prebuilt,
+    /// A system installation found through pkg-config.
+    PkgConfig,
+    /// The sources in `FFMPEG_SOURCE_DIR`, built by the build script.
+    SourceBuild,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Version {
+    pub major: u32,
+    pub minor: u32,
+    pub micro: u32,
+}
+
+impl Version {
+    /// Splits a version as returned by `avcodec_version()` and friends.
+    pub fn from_int(version: u32) -> Version {
+        Version {
+            major: version >> 16,
+            minor: (version >> 8) & 0xff,
+            micro: version & 0xff,
+        }
+    }
+
+    /// The `AV_VERSION_INT` of the version.
+    pub fn to_int(self) -> u32 {
+        self.major << 16 | self.minor << 8 | self.micro
+    }
+
+    /// Whether a library of version `runtime` can be used with headers of this
+    /// version: the major versions must match and the library can't have an
+    /// older minor version.
+    pub fn is_compatible_with(self, runtime: Version) -> bool {
+        self.major == runtime.major && self.minor <= runtime.minor
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.micro)
+    }
+}
+
+/// A library whose runtime version doesn't match the headers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VersionMismatch {
+    pub library: &'static str,
+    pub header: Version,
+    pub runtime: Version,
+}
+
+impl fmt::Display for VersionMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "lib{} {} is used at runtime, but the bindings were generated for {}",
+            self.library, self.runtime, self.header
+        )
+    }
+}
+
+// SOURCE, CONFIGURATION, LIBRARIES, HEADER_VERSIONS and runtime_versions()
+include!(concat!(env!("OUT_DIR"), "/build_info.rs"));
+
+fn mismatches(runtime_versions: Vec<(&'static str, u32)>) -> Result<(), Vec<VersionMismatch>> {
+    let mismatches: Vec<VersionMismatch> = HEADER_VERSIONS
+        .iter()
+        .zip(runtime_versions)
+        .map(|(&(library, header), (_, runtime))| VersionMismatch {
+            library,
+            header,
+            runtime: Version::from_int(runtime),
+        })
+        .filter(|mismatch| !mismatch.header.is_compatible_with(mismatch.runtime))
+        .collect();
+
+    if mismatches.is_empty() {
+        Ok(())
+    } else {
+        Err(mismatches)
+    }
+}
+
+/// Compares the versions the libraries report at runtime with the headers
+/// the bindings were generated from.
+#[cfg(not(feature = "runtime-linking"))]
+pub fn check_runtime() -> Result<(), Vec<VersionMismatch>> {
+    mismatches(runtime_versions())
+}
+
+/// Compares the versions the loaded libraries report with the headers the
+/// bindings were generated from.
+///
+/// Panics if one of the `*_version()` functions couldn't be resolved, see
+/// `Loader::missing_functions`.
+#[cfg(feature = "runtime-linking")]
+pub fn check_runtime(loader: &::Loader) -> Result<(), Vec<VersionMismatch>> {
+    mismatches(runtime_versions(loader))
+}
@@ -5,7 +5,19 @@
 extern crate libc;
 
 include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
+include!(concat!(env!("OUT_DIR"), "/components.rs"));
+include!(concat!(env!("OUT_DIR"), "/versions.rs"));
+include!(concat!(env!("OUT_DIR"), "/license.rs"));
 
 #[macro_use]
 mod avutil;
 pub use avutil::*;
+
+include!(concat!(env!("OUT_DIR"), "/flags.rs"));
+
+pub mod build_info;
+
+#[cfg(feature = "runtime-linking")]
+mod loader;
+#[cfg(feature = "runtime-linking")]
+pub use loader::*;
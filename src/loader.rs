@@ -0,0 +1,121 @@
+use std::ffi::OsString;
+use std::ops::Deref;
+use std::path::Path;
+
+use libloading;
+use libloading::os::unix::{Library, RTLD_LOCAL, RTLD_NOW};
+
+use FFmpegFunctions;
+
+/// A shared library one of the enabled components lives in.
+#[derive(Debug, Clone, Copy)]
+pub struct RuntimeLibrary {
+    pub name: &'static str,
+    pub file_name: &'static str,
+}
+
+include!(concat!(env!("OUT_DIR"), "/runtime_libraries.rs"));
+
+// Looks `name` up in each of the opened libraries, the generated
+// FFmpegFunctions::from_libraries resolves every function with it
+unsafe fn symbol<T: Copy>(libraries: &[Library], name: &[u8]) -> Result<T, libloading::Error> {
+    let mut error = libloading::Error::DlSymUnknown;
+
+    for library in libraries {
+        match library.get::<T>(name) {
+            Ok(symbol) => return Ok(*symbol),
+            Err(e) => error = e,
+        }
+    }
+
+    Err(error)
+}
+
+/// A library the loader could not open.
+#[derive(Debug)]
+pub struct MissingLibrary {
+    pub library: RuntimeLibrary,
+    pub error: libloading::Error,
+}
+
+/// Resolves the FFmpeg functions from the shared libraries at runtime.
+///
+/// Missing libraries and functions don't make loading fail, they are
+/// reported by `missing_libraries` and `missing_functions` instead. Calling
+/// a function that wasn't resolved panics.
+pub struct Loader {
+    functions: FFmpegFunctions,
+    // keeps the libraries open for as long as the functions are used
+    libraries: Vec<Library>,
+    missing_libraries: Vec<MissingLibrary>,
+}
+
+impl Loader {
+    /// Opens the libraries from the dynamic linker's search path.
+    ///
+    /// # Safety
+    ///
+    /// Opening a library runs its initialisers, and the resolved functions
+    /// are only sound if the libraries match the headers the bindings were
+    /// generated from.
+    pub unsafe fn new() -> Loader {
+        Loader::open(|library| OsString::from(library.file_name))
+    }
+
+    /// Opens the libraries from `dir`.
+    ///
+    /// # Safety
+    ///
+    /// See `Loader::new`.
+    pub unsafe fn from_dir<P: AsRef<Path>>(dir: P) -> Loader {
+        Loader::open(|library| dir.as_ref().join(library.file_name).into_os_string())
+    }
+
+    unsafe fn open<F>(path: F) -> Loader
+    where
+        F: Fn(&RuntimeLibrary) -> OsString,
+    {
+        let mut libraries = Vec::new();
+        let mut missing_libraries = Vec::new();
+
+        for library in RUNTIME_LIBRARIES {
+            match Library::open(Some(path(library)), RTLD_NOW | RTLD_LOCAL) {
+                Ok(handle) => libraries.push(handle),
+                Err(error) => missing_libraries.push(MissingLibrary {
+                    library: *library,
+                    error,
+                }),
+            }
+        }
+
+        Loader {
+            functions: FFmpegFunctions::from_libraries(&libraries),
+            libraries,
+            missing_libraries,
+        }
+    }
+
+    pub fn missing_libraries(&self) -> &[MissingLibrary] {
+        &self.missing_libraries
+    }
+
+    pub fn missing_functions(&self) -> Vec<&'static str> {
+        RUNTIME_FUNCTIONS
+            .iter()
+            .cloned()
+            .filter(|name| unsafe { symbol::<*const ()>(&self.libraries, name.as_bytes()) }.is_err())
+            .collect()
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.missing_libraries.is_empty() && self.missing_functions().is_empty()
+    }
+}
+
+impl Deref for Loader {
+    type Target = FFmpegFunctions;
+
+    fn deref(&self) -> &FFmpegFunctions {
+        &self.functions
+    }
+}
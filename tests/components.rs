@@ -0,0 +1,246 @@
+// Checks the component profiles and the feature -> configure flag mapping of
+// the source build against Cargo.toml and the generated scripts
+
+#[path = "../build/components.rs"]
+mod components;
+
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process;
+
+use components::*;
+
+fn read(name: &str) -> String {
+    fs::read_to_string(Path::new(env!("CARGO_MANIFEST_DIR")).join(name)).unwrap()
+}
+
+// (name, enabled features) of the [features] section
+fn cargo_features() -> Vec<(String, String)> {
+    read("Cargo.toml")
+        .lines()
+        .skip_while(|line| *line != "[features]")
+        .skip(1)
+        .take_while(|line| !line.starts_with('['))
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| {
+            let mut parts = line.splitn(2, '=');
+            match (parts.next(), parts.next()) {
+                (Some(name), Some(value)) => Some((name.trim().into(), value.trim().into())),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+fn is_feature(name: &str) -> bool {
+    cargo_features().iter().any(|feature| feature.0 == name)
+}
+
+fn profiles() -> Vec<Profile> {
+    parse_profiles(&read(PROFILES_FILE)).unwrap()
+}
+
+#[test]
+fn feature_vars() {
+    assert_eq!(feature_var("tiny"), "CARGO_FEATURE_TINY");
+    assert_eq!(
+        feature_var("build-lib-fdk-aac"),
+        "CARGO_FEATURE_BUILD_LIB_FDK_AAC"
+    );
+}
+
+#[test]
+fn external_libraries_are_features() {
+    for &(feature, _) in EXTERNAL_LIBRARIES {
+        assert!(is_feature(feature), "{} isn't a feature", feature);
+    }
+}
+
+#[test]
+fn build_features_are_mapped() {
+    // the features only selecting a library, aliases enable the real name
+    let library_features = cargo_features()
+        .into_iter()
+        .filter(|(_, value)| value == "[\"build\"]")
+        .map(|(name, _)| name)
+        .filter(|name| name != "build-disable-x86asm" && !name.starts_with("build-license-"));
+
+    for feature in library_features {
+        assert!(
+            EXTERNAL_LIBRARIES.iter().any(|&(mapped, _)| mapped == feature),
+            "{} has no configure flag",
+            feature
+        );
+    }
+}
+
+#[test]
+fn profiles_are_features() {
+    let profiles = profiles();
+    assert!(!profiles.is_empty());
+
+    for profile in &profiles {
+        assert!(is_feature(&profile.name), "{} isn't a feature", profile.name);
+        assert!(!profile.components.is_empty());
+    }
+}
+
+#[test]
+fn parse_errors() {
+    assert!(parse_profiles("decoder flac").is_err());
+    assert!(parse_profiles("[tiny]\ndecoder").is_err());
+    assert!(parse_profiles("[tiny]\ndecoder flac alac").is_err());
+
+    let profiles = parse_profiles("# comment\n\n[tiny]\n  protocol file\n").unwrap();
+    assert_eq!(
+        profiles,
+        vec![Profile {
+            name: "tiny".into(),
+            components: vec![("protocol".into(), "file".into())],
+        }]
+    );
+}
+
+#[test]
+fn configure_arguments() {
+    let profiles = profiles();
+
+    let args = configure_args(&profiles, |feature| feature == "build-lib-fribidi", &[]);
+    assert!(args.contains(&"--enable-libfribidi".to_string()));
+    assert!(args.contains(&"--disable-sdl2".to_string()));
+    assert!(!args.contains(&"--disable-everything".to_string()));
+    assert!(!args.iter().any(|arg| arg.starts_with("--enable-protocol=")));
+
+    let args = configure_args(
+        &profiles,
+        |feature| feature == "tiny" || feature == "common-audio" || feature == "build-lib-mp3lame",
+        &[],
+    );
+    assert_eq!(args[0], "--disable-everything");
+    assert!(args.contains(&"--enable-protocol=file".to_string()));
+    assert!(args.contains(&"--enable-libmp3lame".to_string()));
+    assert!(args.contains(&"--enable-encoder=libmp3lame".to_string()));
+}
+
+#[test]
+fn selected_components() {
+    let selected = [
+        ("decoder", parse_list("aac, flac mp3")),
+        ("protocol", Vec::new()),
+    ];
+    let args = configure_args(&[], |_| false, &selected);
+    assert_eq!(
+        &args[..4],
+        &[
+            "--disable-everything",
+            "--enable-decoder=aac",
+            "--enable-decoder=flac",
+            "--enable-decoder=mp3",
+        ]
+    );
+
+    // an empty list still starts from nothing
+    let args = configure_args(&[], |_| false, &[("protocol", Vec::new())]);
+    assert_eq!(args[0], "--disable-everything");
+}
+
+// With a stand-in for configure in a directory of its own, as on a clean build
+// where nothing but the sources exists yet
+#[cfg(unix)]
+#[test]
+fn listed_components() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = env::temp_dir().join(format!("ffmpeg4-sys-configure-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let configure = dir.join("configure");
+    fs::write(
+        &configure,
+        "#!/bin/sh\ncase $1 in\n--list-decoders) echo 'aac  flac\npcm_s16le pcm_s24le' ;;\n*) exit 1 ;;\nesac\n",
+    )
+    .unwrap();
+    fs::set_permissions(&configure, fs::Permissions::from_mode(0o755)).unwrap();
+
+    let selected = components::selected_components(&configure, |var| match var {
+        "FFMPEG_ENABLE_DECODERS" => Some("aac, pcm_*".to_string()),
+        "FFMPEG_ENABLE_PROTOCOLS" => Some(String::new()),
+        _ => None,
+    })
+    .unwrap();
+    assert_eq!(
+        selected,
+        vec![("decoder", vec!["aac".to_string(), "pcm_*".to_string()]), ("protocol", Vec::new())]
+    );
+
+    let error = components::selected_components(&configure, |var| match var {
+        "FFMPEG_ENABLE_DECODERS" => Some("aac,h264,vp*".to_string()),
+        _ => None,
+    })
+    .unwrap_err();
+    assert_eq!(error.to_string(), "FFMPEG_ENABLE_DECODERS lists unknown decoders: h264, vp*");
+
+    // the stand-in only knows the decoders
+    let error = components::selected_components(&configure, |var| match var {
+        "FFMPEG_ENABLE_MUXERS" => Some("mp4".to_string()),
+        _ => None,
+    })
+    .unwrap_err();
+    assert_eq!(error.to_string(), "configure --list-muxers failed");
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn component_vars() {
+    for &(var, kind) in COMPONENT_VARS {
+        assert_eq!(var, format!("FFMPEG_ENABLE_{}S", kind.to_uppercase()));
+    }
+}
+
+#[test]
+fn component_names() {
+    assert_eq!(parse_list(" aac,flac\n mp3 ,, "), vec!["aac", "flac", "mp3"]);
+    assert!(parse_list("").is_empty());
+
+    let known = parse_list("aac  aac_latm  pcm_s16le  pcm_s16le_planar");
+    assert!(is_known("aac", &known));
+    assert!(is_known("pcm_*", &known));
+    assert!(is_known("*_planar", &known));
+    assert!(is_known("pcm*le", &known));
+    assert!(!is_known("aa", &known));
+    assert!(!is_known("h264", &known));
+    assert!(!is_known("vp*", &known));
+}
+
+#[test]
+fn extra_args() {
+    assert_eq!(
+        split_args(" --enable-small  --disable-iconv ").unwrap(),
+        vec!["--enable-small", "--disable-iconv"]
+    );
+    assert_eq!(
+        split_args("--extra-cflags=\"-I/opt/x -DFOO\" --extra-ldflags='-L/opt/x/lib -lm'").unwrap(),
+        vec!["--extra-cflags=-I/opt/x -DFOO", "--extra-ldflags=-L/opt/x/lib -lm"]
+    );
+    assert_eq!(
+        split_args(r#"--extra-cflags="-DNAME=\"x\" -DP=\n" a\ b '' "#).unwrap(),
+        vec![r#"--extra-cflags=-DNAME="x" -DP=\n"#, "a b", ""]
+    );
+    assert!(split_args("").unwrap().is_empty());
+    assert!(split_args("--extra-cflags=\"-I/opt/x").is_err());
+    assert!(split_args("'").is_err());
+}
+
+#[test]
+fn scripts_are_up_to_date() {
+    let profiles = profiles();
+
+    for &(name, extra_args) in SCRIPTS {
+        assert!(
+            read(name) == configure_script(&profiles, extra_args),
+            "{} is out of date, build with FFMPEG_REGENERATE_SCRIPTS=1",
+            name
+        );
+    }
+}
@@ -0,0 +1,268 @@
+// Checks the conversion of the header comments to rustdoc, with comments
+// shaped like the ones in the FFmpeg headers, as bindgen passes them
+
+#[path = "../build/doxygen.rs"]
+mod doxygen;
+
+use std::fs;
+use std::path::Path;
+
+use doxygen::to_rustdoc;
+
+fn lines(lines: &[&str]) -> String {
+    lines.join("\n")
+}
+
+// Every code block has to be fenced as text, anything else would be run as a
+// doctest
+fn assert_fenced_as_text(rustdoc: &str) {
+    let mut in_code = false;
+    let mut previous_blank = true;
+    for line in rustdoc.lines() {
+        if line.trim_start().starts_with("```") {
+            assert!(in_code || line == " ```text", "unfenced code in:\n{}", rustdoc);
+            in_code = !in_code;
+        } else if !in_code {
+            // only an indented line after a blank one starts a code block
+            let indented = line.starts_with("     ");
+            assert!(!(indented && previous_blank), "indented code in:\n{}", rustdoc);
+        }
+        previous_blank = line.trim().is_empty();
+    }
+    assert!(!in_code, "unterminated code in:\n{}", rustdoc);
+}
+
+#[test]
+fn labels() {
+    let comment = lines(&[
+        " Allocate an AVPacket and set its fields to default values.",
+        "",
+        " @return An AVPacket filled with default values or NULL on failure.",
+        "",
+        " @note this only allocates the AVPacket itself, not the data buffers.",
+        " @deprecated use av_packet_alloc()",
+        " @warning: dst is not unreferenced",
+        " @see av_new_packet",
+    ]);
+
+    assert_eq!(
+        to_rustdoc(&comment),
+        lines(&[
+            " Allocate an AVPacket and set its fields to default values.",
+            "",
+            " Returns An AVPacket filled with default values or NULL on failure.",
+            "",
+            " Note: this only allocates the AVPacket itself, not the data buffers.",
+            " Deprecated: use av_packet_alloc()",
+            " Warning: dst is not unreferenced",
+            " See also: av_new_packet",
+        ])
+    );
+}
+
+#[test]
+fn code() {
+    let comment = lines(&[
+        " Example:",
+        " @code",
+        " AVDictionary *options = NULL;",
+        " if (av_dict_set(&options, \"video_size\", \"640x480\", 0) < 0)",
+        " @endcode",
+        " @code{.c}",
+        " x[0] = 1;",
+        " @endcode",
+    ]);
+    let rustdoc = to_rustdoc(&comment);
+
+    assert_eq!(
+        rustdoc,
+        lines(&[
+            " Example:",
+            " ```text",
+            " AVDictionary *options = NULL;",
+            " if (av_dict_set(&options, \"video_size\", \"640x480\", 0) < 0)",
+            " ```",
+            " ```text",
+            " x[0] = 1;",
+            " ```",
+        ])
+    );
+    assert_fenced_as_text(&rustdoc);
+}
+
+#[test]
+fn unterminated_code() {
+    let rustdoc = to_rustdoc(" @code\n av_free(ptr);");
+    assert_eq!(rustdoc, lines(&[" ```text", " av_free(ptr);", " ```"]));
+    assert_fenced_as_text(&rustdoc);
+}
+
+#[test]
+fn indented_code() {
+    let comment = lines(&[
+        " Example:",
+        "",
+        "     AVDictionary *d = NULL;",
+        "",
+        "\tav_dict_set(&d, \"foo\", \"bar\", 0);",
+        "",
+        " Then free it.",
+    ]);
+    let rustdoc = to_rustdoc(&comment);
+
+    assert_eq!(
+        rustdoc,
+        lines(&[
+            " Example:",
+            "",
+            " ```text",
+            " AVDictionary *d = NULL;",
+            "",
+            " av_dict_set(&d, \"foo\", \"bar\", 0);",
+            "",
+            " ```",
+            " Then free it.",
+        ])
+    );
+    assert_fenced_as_text(&rustdoc);
+}
+
+#[test]
+fn params() {
+    let comment = lines(&[
+        " Read encoded data from the encoder.",
+        "",
+        " @param[in]  avctx codec context",
+        " @param[out] avpkt This will be set to a reference-counted packet allocated by the",
+        "                   encoder.",
+        " @param flags      AV_PKT_FLAG_*",
+        " @retval 0               success, a packet was returned",
+        " @retval AVERROR(EAGAIN) output is not available in the current state",
+        " @retval \"another negative error code\" legitimate encoding errors",
+    ]);
+    let rustdoc = to_rustdoc(&comment);
+
+    assert_eq!(
+        rustdoc,
+        lines(&[
+            " Read encoded data from the encoder.",
+            "",
+            " - `avctx`: codec context",
+            " - `avpkt`: This will be set to a reference-counted packet allocated by the",
+            "                   encoder.",
+            " - `flags`: AV_PKT_FLAG_*",
+            " - Returns `0`: success, a packet was returned",
+            " - Returns `AVERROR(EAGAIN)`: output is not available in the current state",
+            " - Returns `another negative error code`: legitimate encoding errors",
+        ])
+    );
+    assert_fenced_as_text(&rustdoc);
+}
+
+#[test]
+fn inline_commands() {
+    let comment = lines(&[
+        " Supply raw packet data to a decoder, see @ref avcodec_receive_frame()).",
+        " Only @p buf_size bytes of @a buf are used (@ref AVPacket.pts, @c AV_NOPTS_VALUE).",
+        " Allocates an array of {@code nb_bboxes} AVDetectionBBox in {@code frame}.",
+        " Accessed with @em av_opt_get, @b never directly, @see AVOptionRanges",
+    ]);
+
+    assert_eq!(
+        to_rustdoc(&comment),
+        lines(&[
+            " Supply raw packet data to a decoder, see `avcodec_receive_frame()`).",
+            " Only `buf_size` bytes of `buf` are used (`AVPacket.pts`, `AV_NOPTS_VALUE`).",
+            " Allocates an array of `nb_bboxes` AVDetectionBBox in `frame`.",
+            " Accessed with *av_opt_get*, **never** directly, see AVOptionRanges",
+        ])
+    );
+}
+
+#[test]
+fn escaping() {
+    let comment = lines(&[
+        " pointer to the planes, data[0] is the first one.",
+        " Returns <0 on error, and @p data[i] is left as it is.",
+    ]);
+
+    assert_eq!(
+        to_rustdoc(&comment),
+        lines(&[
+            " pointer to the planes, data\\[0\\] is the first one.",
+            " Returns \\<0 on error, and `data[i]` is left as it is.",
+        ])
+    );
+
+    // ///< comments document the member before them
+    assert_eq!(
+        to_rustdoc("< number of bytes per line, <0 for bottom-up images"),
+        " number of bytes per line, \\<0 for bottom-up images"
+    );
+}
+
+#[test]
+fn structure() {
+    let comment = lines(&[
+        " @defgroup lavc_core Core functions/structures.",
+        " @ingroup libavc",
+        "",
+        " Basic definitions.",
+        " @{",
+        " @section lavf_decoding_open Opening a media file",
+        " @section urls",
+        " @subsection lavf_io_dirlist_read Reading entries",
+        " @li one item",
+        " @todo fix it",
+    ]);
+
+    assert_eq!(
+        to_rustdoc(&comment),
+        lines(&[
+            " Basic definitions.",
+            " ## Opening a media file",
+            " ### Reading entries",
+            " - one item",
+            " TODO: fix it",
+        ])
+    );
+}
+
+// The /** */ comments of a header, without the * starting each line
+fn header_comments(header: &str) -> Vec<String> {
+    header
+        .split("/**")
+        .skip(1)
+        .filter_map(|rest| rest.find("*/").map(|end| &rest[..end]))
+        .map(|comment| {
+            comment
+                .lines()
+                .map(|line| {
+                    let line = line.trim_start();
+                    line.strip_prefix('*').unwrap_or(line).trim_end()
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+                .trim_matches('\n')
+                .to_string()
+        })
+        .collect()
+}
+
+#[test]
+fn bundled_headers() {
+    let include = Path::new(env!("CARGO_MANIFEST_DIR")).join("builds/x86_64-unknown-linux-gnu/include");
+    let mut count = 0;
+
+    for library in fs::read_dir(include).unwrap() {
+        for header in fs::read_dir(library.unwrap().path()).unwrap() {
+            let header = fs::read_to_string(header.unwrap().path()).unwrap();
+            for comment in header_comments(&header) {
+                assert_fenced_as_text(&to_rustdoc(&comment));
+                count += 1;
+            }
+        }
+    }
+
+    assert!(count > 1000);
+}
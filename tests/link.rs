@@ -0,0 +1,90 @@
+// Calls into every enabled library, so a bound symbol missing from the
+// libraries fails to link
+#![cfg(not(feature = "runtime-linking"))]
+
+extern crate ffmpeg4_sys;
+
+use std::ffi::CStr;
+
+use ffmpeg4_sys::*;
+
+#[test]
+fn avutil() {
+    unsafe {
+        assert_ne!(avutil_version(), 0);
+
+        let configuration = CStr::from_ptr(avutil_configuration());
+        assert!(configuration.to_str().is_ok());
+
+        let license = CStr::from_ptr(avutil_license()).to_str().unwrap();
+        assert_eq!(license, FFMPEG_LICENSE);
+    }
+}
+
+#[test]
+fn inline_functions() {
+    unsafe {
+        let q = av_make_q(1, 4);
+        assert_eq!(av_q2d(q), 0.25);
+        assert_eq!(av_cmp_q(q, av_inv_q(av_make_q(4, 1))), 0);
+    }
+}
+
+#[cfg(feature = "avcodec")]
+#[test]
+fn avcodec() {
+    unsafe {
+        assert_ne!(avcodec_version(), 0);
+        assert!(!CStr::from_ptr(avcodec_configuration()).to_bytes().is_empty());
+    }
+}
+
+#[cfg(feature = "avformat")]
+#[test]
+fn avformat() {
+    unsafe {
+        assert_ne!(avformat_version(), 0);
+        assert!(!CStr::from_ptr(avformat_configuration()).to_bytes().is_empty());
+    }
+}
+
+#[cfg(feature = "avdevice")]
+#[test]
+fn avdevice() {
+    unsafe {
+        assert_ne!(avdevice_version(), 0);
+    }
+}
+
+#[cfg(feature = "avfilter")]
+#[test]
+fn avfilter() {
+    unsafe {
+        assert_ne!(avfilter_version(), 0);
+    }
+}
+
+#[cfg(feature = "postproc")]
+#[test]
+fn postproc() {
+    unsafe {
+        assert_ne!(postproc_version(), 0);
+    }
+}
+
+#[cfg(feature = "swresample")]
+#[test]
+fn swresample() {
+    unsafe {
+        assert_ne!(swresample_version(), 0);
+        assert!(!CStr::from_ptr(swresample_configuration()).to_bytes().is_empty());
+    }
+}
+
+#[cfg(feature = "swscale")]
+#[test]
+fn swscale() {
+    unsafe {
+        assert_ne!(swscale_version(), 0);
+    }
+}
@@ -0,0 +1,72 @@
+#![cfg(not(feature = "runtime-linking"))]
+
+extern crate ffmpeg4_sys;
+
+use ffmpeg4_sys::build_info::{self, Version, HEADER_VERSIONS};
+use ffmpeg4_sys::*;
+
+fn header_version(library: &str) -> Version {
+    HEADER_VERSIONS
+        .iter()
+        .find(|&&(name, _)| name == library)
+        .map(|&(_, version)| version)
+        .unwrap()
+}
+
+#[test]
+fn runtime_matches_headers() {
+    if let Err(mismatches) = build_info::check_runtime() {
+        for mismatch in &mismatches {
+            println!("{}", mismatch);
+        }
+        panic!("{} libraries don't match their headers", mismatches.len());
+    }
+}
+
+#[test]
+fn avutil() {
+    let runtime = Version::from_int(unsafe { avutil_version() });
+    assert_eq!(runtime, header_version("avutil"));
+}
+
+#[cfg(feature = "avcodec")]
+#[test]
+fn avcodec() {
+    let runtime = Version::from_int(unsafe { avcodec_version() });
+    assert_eq!(runtime, header_version("avcodec"));
+}
+
+#[cfg(feature = "avformat")]
+#[test]
+fn avformat() {
+    let runtime = Version::from_int(unsafe { avformat_version() });
+    assert_eq!(runtime, header_version("avformat"));
+}
+
+#[cfg(feature = "avdevice")]
+#[test]
+fn avdevice() {
+    let runtime = Version::from_int(unsafe { avdevice_version() });
+    assert_eq!(runtime, header_version("avdevice"));
+}
+
+#[cfg(feature = "avfilter")]
+#[test]
+fn avfilter() {
+    let runtime = Version::from_int(unsafe { avfilter_version() });
+    assert_eq!(runtime, header_version("avfilter"));
+}
+
+#[cfg(feature = "swresample")]
+#[test]
+fn swresample() {
+    let runtime = Version::from_int(unsafe { swresample_version() });
+    assert_eq!(runtime, header_version("swresample"));
+}
+
+#[cfg(feature = "swscale")]
+#[test]
+fn swscale() {
+    let runtime = Version::from_int(unsafe { swscale_version() });
+    assert_eq!(runtime, header_version("swscale"));
+}